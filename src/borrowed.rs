@@ -0,0 +1,880 @@
+//! Borrowed, zero-copy views of encodings and PDUs
+//!
+//! The types in [crate::encodings] and [crate::pdu] own their data, so decoding allocates for every ID, OctetString,
+//! and list element. The views in this module borrow from the received bytes instead and decode lazily, which makes
+//! answering Get, GetNext, and GetBulk requests possible without touching the heap. Every view validates the bytes
+//! when it is created and can be converted into the owned type with `into_owned()`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use agentx::borrowed::GetNextRef;
+//! # use agentx::encodings::{SearchRange, SearchRangeList, ID};
+//! # use agentx::pdu::GetNext;
+//! # use std::str::FromStr;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let start = ID::from_str("1.3.6.1.4.1.23302.1")?;
+//! let bytes = GetNext::new(SearchRangeList(vec![SearchRange::new(start.clone(), ID::default())])).to_bytes()?;
+//!
+//! let get = GetNextRef::from_bytes(&bytes)?;
+//! for sr in get.sr.iter() {
+//!     assert_eq!(sr.start, start);
+//!     assert!(sr.end.is_null());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::str::{self, Utf8Error};
+
+use crate::encodings::{
+    Context, OctetString, SearchRange, SearchRangeList, Value, VarBind, VarBindList, ID,
+};
+use crate::error::ResultExt;
+use crate::pdu::{offset, payload, Get, GetBulk, GetNext, Header, TestSet};
+use crate::{
+    bytes_to_i32, bytes_to_u16, bytes_to_u32, bytes_to_u64, skip, truncated, ByteOrder, Error,
+};
+
+// the sub-identifiers a non-zero prefix expands to, the prefix itself is appended
+const INTERNET: [u32; 4] = [1, 3, 6, 1];
+
+/// Borrowed [ID]
+#[derive(Clone, Copy, Debug)]
+pub struct IdRef<'a> {
+    prefix: u8,
+    include: u8,
+    // n_subid sub-identifiers, size already checked
+    b: &'a [u8],
+    bo: ByteOrder,
+}
+
+impl<'a> IdRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        if b.len() < size_of::<u32>() {
+            return Err(truncated(b, size_of::<u32>()));
+        }
+        let (n_subid, prefix, include) = (b[0], b[1], b[2]);
+        // the limit applies to the normalized ID, a prefix expands to 5 more sub-identifiers
+        let normalized = n_subid as usize + if prefix != 0 { 5 } else { 0 };
+        if normalized > crate::encodings::id::MAX_SUB_IDS {
+            return Err(Error::IdTooLong {
+                offset: 0,
+                n_subid: normalized,
+            });
+        }
+
+        let b = skip(b, size_of::<u32>())?;
+        let needed = n_subid as usize * size_of::<u32>();
+        let b = b.get(..needed).ok_or_else(|| truncated(b, needed)).at(4)?;
+
+        Ok(Self {
+            prefix,
+            include,
+            b,
+            bo: *bo,
+        })
+    }
+
+    /// used in SearchRange to indicate that the specified ID should be included in the results
+    pub fn include(&self) -> u8 {
+        self.include
+    }
+
+    /// number of sub-identifiers, including the ones the prefix expands to
+    pub fn len(&self) -> usize {
+        let prefix = if self.prefix != 0 {
+            INTERNET.len() + 1
+        } else {
+            0
+        };
+        prefix + self.b.len() / size_of::<u32>()
+    }
+
+    /// Returns true if there are no sub-identifiers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// check for "null Object Identifier"
+    pub fn is_null(&self) -> bool {
+        self.is_empty()
+    }
+
+    /// iterate over the (normalized) sub-identifiers
+    pub fn sub_ids(&self) -> SubIds<'a> {
+        let prefix = if self.prefix != 0 { &INTERNET[..] } else { &[] };
+
+        SubIds {
+            prefix: prefix.iter(),
+            last: if self.prefix != 0 {
+                Some(self.prefix as u32)
+            } else {
+                None
+            },
+            b: self.b,
+            bo: self.bo,
+        }
+    }
+
+    /// convert into an owned [ID]
+    pub fn into_owned(self) -> ID {
+        let mut id = ID::from_bytes(&self.wire(), &self.bo).expect("validated on creation");
+        id.include = self.include;
+        id
+    }
+
+    // the wire representation, only used for conversion into the owned type
+    fn wire(self) -> Vec<u8> {
+        let n_subid = (self.b.len() / size_of::<u32>()) as u8;
+        let mut b = vec![n_subid, self.prefix, self.include, 0];
+        b.extend(self.b);
+        b
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        size_of::<u32>() + self.b.len()
+    }
+}
+
+impl PartialEq for IdRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sub_ids().eq(other.sub_ids())
+    }
+}
+
+impl PartialEq<ID> for IdRef<'_> {
+    fn eq(&self, other: &ID) -> bool {
+        self.sub_ids().eq(other.iter().copied())
+    }
+}
+
+impl PartialOrd<ID> for IdRef<'_> {
+    fn partial_cmp(&self, other: &ID) -> Option<Ordering> {
+        Some(self.sub_ids().cmp(other.iter().copied()))
+    }
+}
+
+/// Iterator over the sub-identifiers of an [IdRef]
+#[derive(Clone, Debug)]
+pub struct SubIds<'a> {
+    prefix: std::slice::Iter<'static, u32>,
+    last: Option<u32>,
+    b: &'a [u8],
+    bo: ByteOrder,
+}
+
+impl Iterator for SubIds<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if let Some(s) = self.prefix.next() {
+            return Some(*s);
+        }
+        if let Some(s) = self.last.take() {
+            return Some(s);
+        }
+
+        let s = bytes_to_u32(self.b, &self.bo).ok()?;
+        self.b = &self.b[size_of::<u32>()..]; // size already checked
+        Some(s)
+    }
+}
+
+/// Borrowed [OctetString], without padding
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct OctetStringRef<'a>(pub &'a [u8]);
+
+impl<'a> OctetStringRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        let length = bytes_to_u32(b, bo)? as usize;
+        let b = skip(b, size_of::<u32>())?;
+        let b = b
+            .get(..length)
+            .ok_or_else(|| truncated(b, length))
+            .at(size_of::<u32>())?;
+
+        Ok(Self(b))
+    }
+
+    /// the octets
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// the octets as `&str`, fails if they are not valid UTF-8
+    pub fn as_str(&self) -> Result<&'a str, Utf8Error> {
+        str::from_utf8(self.0)
+    }
+
+    /// number of octets
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if there are no octets.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// convert into an owned [OctetString]
+    pub fn into_owned(self) -> OctetString {
+        OctetString::from(self.0)
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        size_of::<u32>() + (self.0.len() + 3) / 4 * 4
+    }
+}
+
+/// Borrowed [SearchRange]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchRangeRef<'a> {
+    /// start of the range
+    pub start: IdRef<'a>,
+    /// end of the range (exclusive), null for no upper bound
+    pub end: IdRef<'a>,
+}
+
+impl<'a> SearchRangeRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        let start = IdRef::from_bytes(b, bo)?;
+        let offset = start.byte_size();
+        let end = IdRef::from_bytes(skip(b, offset)?, bo).at(offset)?;
+
+        Ok(Self { start, end })
+    }
+
+    /// check if `id` lies within the range, see [SearchRange::contains]
+    pub fn contains(&self, id: &ID) -> bool {
+        let after_start = self.start < *id || (self.start.include != 0 && self.start == *id);
+        let before_end = self.end.is_null() || self.end > *id;
+
+        after_start && before_end
+    }
+
+    /// convert into an owned [SearchRange]
+    pub fn into_owned(self) -> SearchRange {
+        SearchRange::new(self.start.into_owned(), self.end.into_owned())
+    }
+
+    pub(crate) fn byte_size(&self) -> usize {
+        self.start.byte_size() + self.end.byte_size()
+    }
+}
+
+/// Borrowed [SearchRangeList], the SearchRanges are decoded while iterating
+#[derive(Clone, Copy, Debug)]
+pub struct SearchRangeListRef<'a> {
+    b: &'a [u8],
+    bo: ByteOrder,
+    len: usize,
+}
+
+impl<'a> SearchRangeListRef<'a> {
+    /// decode from bytes, all of `b` belongs to the list
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        let mut offset = 0;
+        let mut len = 0;
+
+        while offset < b.len() {
+            let rest = &b[offset..];
+            let sr = SearchRangeRef::from_bytes(rest, bo).at(offset)?;
+            // the last element has to be complete, including its padding
+            skip(rest, sr.byte_size()).at(offset)?;
+            offset += sr.byte_size();
+            len += 1;
+        }
+
+        Ok(Self { b, bo: *bo, len })
+    }
+
+    /// iterate over the SearchRanges
+    pub fn iter(&self) -> SearchRanges<'a> {
+        SearchRanges {
+            b: self.b,
+            bo: self.bo,
+        }
+    }
+
+    /// number of SearchRanges
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the list contains no SearchRanges.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// convert into an owned [SearchRangeList]
+    pub fn into_owned(self) -> SearchRangeList {
+        SearchRangeList(self.iter().map(SearchRangeRef::into_owned).collect())
+    }
+}
+
+impl<'a> IntoIterator for SearchRangeListRef<'a> {
+    type Item = SearchRangeRef<'a>;
+    type IntoIter = SearchRanges<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the SearchRanges of a [SearchRangeListRef]
+#[derive(Clone, Debug)]
+pub struct SearchRanges<'a> {
+    b: &'a [u8],
+    bo: ByteOrder,
+}
+
+impl<'a> Iterator for SearchRanges<'a> {
+    type Item = SearchRangeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.b.is_empty() {
+            return None;
+        }
+        // validated on creation, so this does not fail
+        let sr = SearchRangeRef::from_bytes(self.b, &self.bo).ok()?;
+        self.b = self.b.get(sr.byte_size()..).unwrap_or(&[]);
+        Some(sr)
+    }
+}
+
+/// Borrowed [Value]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    /// 4 byte (signed) integer type
+    Integer(i32),
+    /// OctetString
+    OctetString(OctetStringRef<'a>),
+    /// null type (does not contain encoded value)
+    Null,
+    /// Object identifier
+    ObjectIdentifier(IdRef<'a>),
+    /// IP address octets need to be orderd most significant to least significant
+    IpAddress(OctetStringRef<'a>),
+    /// 4 byte (unsigned) integer type
+    Counter32(u32),
+    /// 4 byte (unsigned) integer type
+    Gauge32(u32),
+    /// 4 byte (signed) integer type
+    TimeTicks(i32),
+    /// Opaque type consisting of a OctetString
+    Opaque(OctetStringRef<'a>),
+    /// 8 byte (unsigned) integer type
+    Counter64(u64),
+    /// NoSuchObject (does not contain encoded value)
+    NoSuchObject,
+    /// NoSuchInstance (does not contain encoded value)
+    NoSuchInstance,
+    /// EndOfMibView (does not contain encoded value)
+    EndOfMibView,
+}
+
+impl ValueRef<'_> {
+    /// convert into an owned [Value]
+    pub fn into_owned(self) -> Value {
+        match self {
+            Self::Integer(i) => Value::Integer(i),
+            Self::OctetString(s) => Value::OctetString(s.into_owned()),
+            Self::Null => Value::Null,
+            Self::ObjectIdentifier(i) => Value::ObjectIdentifier(i.into_owned()),
+            Self::IpAddress(a) => Value::IpAddress(a.into_owned()),
+            Self::Counter32(c) => Value::Counter32(c),
+            Self::Gauge32(g) => Value::Gauge32(g),
+            Self::TimeTicks(t) => Value::TimeTicks(t),
+            Self::Opaque(o) => Value::Opaque(o.into_owned()),
+            Self::Counter64(c) => Value::Counter64(c),
+            Self::NoSuchObject => Value::NoSuchObject,
+            Self::NoSuchInstance => Value::NoSuchInstance,
+            Self::EndOfMibView => Value::EndOfMibView,
+        }
+    }
+
+    fn byte_size(&self) -> usize {
+        match self {
+            Self::Integer(_) | Self::Counter32(_) | Self::Gauge32(_) | Self::TimeTicks(_) => {
+                size_of::<u32>()
+            }
+            Self::Counter64(_) => size_of::<u64>(),
+            Self::OctetString(s) | Self::IpAddress(s) | Self::Opaque(s) => s.byte_size(),
+            Self::ObjectIdentifier(i) => i.byte_size(),
+            Self::Null | Self::NoSuchObject | Self::NoSuchInstance | Self::EndOfMibView => 0,
+        }
+    }
+}
+
+/// Borrowed [VarBind]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VarBindRef<'a> {
+    /// name of the variable
+    pub name: IdRef<'a>,
+    /// value of the variable
+    pub data: ValueRef<'a>,
+}
+
+impl<'a> VarBindRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        let ty = bytes_to_u16(b, bo)?;
+        let b = skip(b, size_of::<u32>())?; // type + reserved
+
+        let name = IdRef::from_bytes(b, bo).at(size_of::<u32>())?;
+        let offset = size_of::<u32>() + name.byte_size();
+        let b = skip(b, name.byte_size()).at(size_of::<u32>())?;
+
+        let data = match ty {
+            2 => ValueRef::Integer(bytes_to_i32(b, bo).at(offset)?),
+            4 => ValueRef::OctetString(OctetStringRef::from_bytes(b, bo).at(offset)?),
+            5 => ValueRef::Null,
+            6 => ValueRef::ObjectIdentifier(IdRef::from_bytes(b, bo).at(offset)?),
+            64 => ValueRef::IpAddress(OctetStringRef::from_bytes(b, bo).at(offset)?),
+            65 => ValueRef::Counter32(bytes_to_u32(b, bo).at(offset)?),
+            66 => ValueRef::Gauge32(bytes_to_u32(b, bo).at(offset)?),
+            67 => ValueRef::TimeTicks(bytes_to_i32(b, bo).at(offset)?),
+            68 => ValueRef::Opaque(OctetStringRef::from_bytes(b, bo).at(offset)?),
+            70 => ValueRef::Counter64(bytes_to_u64(b, bo).at(offset)?),
+            128 => ValueRef::NoSuchObject,
+            129 => ValueRef::NoSuchInstance,
+            130 => ValueRef::EndOfMibView,
+            _ => return Err(Error::UnknownValueType { offset: 0, ty }),
+        };
+
+        Ok(Self { name, data })
+    }
+
+    /// convert into an owned [VarBind]
+    pub fn into_owned(self) -> VarBind {
+        VarBind::new(self.name.into_owned(), self.data.into_owned())
+    }
+
+    fn byte_size(&self) -> usize {
+        size_of::<u32>() + self.name.byte_size() + self.data.byte_size()
+    }
+}
+
+/// Borrowed [VarBindList], the VarBinds are decoded while iterating
+#[derive(Clone, Copy, Debug)]
+pub struct VarBindListRef<'a> {
+    b: &'a [u8],
+    bo: ByteOrder,
+    len: usize,
+}
+
+impl<'a> VarBindListRef<'a> {
+    /// decode from bytes, all of `b` belongs to the list
+    pub fn from_bytes(b: &'a [u8], bo: &ByteOrder) -> Result<Self, Error> {
+        let mut offset = 0;
+        let mut len = 0;
+
+        while offset < b.len() {
+            let rest = &b[offset..];
+            let vb = VarBindRef::from_bytes(rest, bo).at(offset)?;
+            // the last element has to be complete, including its padding
+            skip(rest, vb.byte_size()).at(offset)?;
+            offset += vb.byte_size();
+            len += 1;
+        }
+
+        Ok(Self { b, bo: *bo, len })
+    }
+
+    /// iterate over the VarBinds
+    pub fn iter(&self) -> VarBinds<'a> {
+        VarBinds {
+            b: self.b,
+            bo: self.bo,
+        }
+    }
+
+    /// number of VarBinds
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the list contains no VarBinds.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// convert into an owned [VarBindList]
+    pub fn into_owned(self) -> VarBindList {
+        VarBindList(self.iter().map(VarBindRef::into_owned).collect())
+    }
+}
+
+impl<'a> IntoIterator for VarBindListRef<'a> {
+    type Item = VarBindRef<'a>;
+    type IntoIter = VarBinds<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the VarBinds of a [VarBindListRef]
+#[derive(Clone, Debug)]
+pub struct VarBinds<'a> {
+    b: &'a [u8],
+    bo: ByteOrder,
+}
+
+impl<'a> Iterator for VarBinds<'a> {
+    type Item = VarBindRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.b.is_empty() {
+            return None;
+        }
+        // validated on creation, so this does not fail
+        let vb = VarBindRef::from_bytes(self.b, &self.bo).ok()?;
+        self.b = self.b.get(vb.byte_size()..).unwrap_or(&[]);
+        Some(vb)
+    }
+}
+
+// header and context of a PDU, followed by the remaining payload
+struct Prefix<'a> {
+    header: Header,
+    context: Option<OctetStringRef<'a>>,
+    b: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Prefix<'a> {
+    fn from_bytes(b: &'a [u8]) -> Result<Self, Error> {
+        let header = Header::from_bytes(b)?;
+        let bo = header.byte_order();
+        let p = payload(&header, b)?;
+        let mut b = p;
+
+        let context = match header.flags.non_default_context() {
+            false => None,
+            true => Some(OctetStringRef::from_bytes(b, &bo).at(offset(p, b))?),
+        };
+        if let Some(c) = &context {
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
+        }
+
+        Ok(Self {
+            header,
+            context,
+            b,
+            offset: offset(p, b),
+        })
+    }
+}
+
+fn context_into_owned(context: Option<OctetStringRef<'_>>) -> Option<Context> {
+    context.map(|c| Context(c.into_owned()))
+}
+
+macro_rules! get_alike_ref {
+    ($(#[$doc:meta])* $name:ident, $owned:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug)]
+        pub struct $name<'a> {
+            /// Header of the PDU
+            pub header: Header,
+            /// optional non-default context
+            pub context: Option<OctetStringRef<'a>>,
+            /// SearchRangeList containing the requested variables
+            pub sr: SearchRangeListRef<'a>,
+        }
+
+        impl<'a> $name<'a> {
+            /// decode from bytes
+            pub fn from_bytes(b: &'a [u8]) -> Result<Self, Error> {
+                let Prefix {
+                    header,
+                    context,
+                    b,
+                    offset,
+                } = Prefix::from_bytes(b)?;
+                let sr = SearchRangeListRef::from_bytes(b, &header.byte_order()).at(offset)?;
+
+                Ok(Self {
+                    header,
+                    context,
+                    sr,
+                })
+            }
+
+            /// convert into the owned PDU
+            pub fn into_owned(self) -> $owned {
+                let mut pdu = $owned::new(self.sr.into_owned());
+                pdu.header = self.header;
+                pdu.context = context_into_owned(self.context);
+                pdu
+            }
+        }
+    };
+}
+
+get_alike_ref!(
+    /// Borrowed [Get] PDU
+    GetRef,
+    Get
+);
+get_alike_ref!(
+    /// Borrowed [GetNext] PDU
+    GetNextRef,
+    GetNext
+);
+
+/// Borrowed [GetBulk] PDU
+#[derive(Clone, Debug)]
+pub struct GetBulkRef<'a> {
+    /// Header of the PDU
+    pub header: Header,
+    /// optional non-default context
+    pub context: Option<OctetStringRef<'a>>,
+    /// number of variables in the SearchRangeList that are not repeaters
+    pub non_repeaters: u16,
+    /// maximum number of repetitions requested for repeating variables
+    pub max_repetitions: u16,
+    /// SearchRangeList containing the requested variables
+    pub sr: SearchRangeListRef<'a>,
+}
+
+impl<'a> GetBulkRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8]) -> Result<Self, Error> {
+        let Prefix {
+            header,
+            context,
+            b,
+            offset,
+        } = Prefix::from_bytes(b)?;
+        let bo = header.byte_order();
+
+        if b.len() < size_of::<u32>() {
+            return Err(truncated(b, size_of::<u32>())).at(offset);
+        }
+        let (non_repeaters, max_repetitions) = (bytes_to_u16(b, &bo)?, bytes_to_u16(&b[2..], &bo)?); // size already checked
+        let b = &b[size_of::<u32>()..];
+
+        let sr = SearchRangeListRef::from_bytes(b, &bo).at(offset + size_of::<u32>())?;
+
+        Ok(Self {
+            header,
+            context,
+            non_repeaters,
+            max_repetitions,
+            sr,
+        })
+    }
+
+    /// convert into an owned [GetBulk]
+    pub fn into_owned(self) -> GetBulk {
+        let mut pdu = GetBulk::new(self.sr.into_owned());
+        pdu.header = self.header;
+        pdu.context = context_into_owned(self.context);
+        pdu.non_repeaters = self.non_repeaters;
+        pdu.max_repetitions = self.max_repetitions;
+        pdu
+    }
+}
+
+/// Borrowed [TestSet] PDU
+#[derive(Clone, Debug)]
+pub struct TestSetRef<'a> {
+    /// Header of the PDU
+    pub header: Header,
+    /// optional non-default context
+    pub context: Option<OctetStringRef<'a>>,
+    /// VarBindList containing the requested VarBinds
+    pub vb: VarBindListRef<'a>,
+}
+
+impl<'a> TestSetRef<'a> {
+    /// decode from bytes
+    pub fn from_bytes(b: &'a [u8]) -> Result<Self, Error> {
+        let Prefix {
+            header,
+            context,
+            b,
+            offset,
+        } = Prefix::from_bytes(b)?;
+        let vb = VarBindListRef::from_bytes(b, &header.byte_order()).at(offset)?;
+
+        Ok(Self {
+            header,
+            context,
+            vb,
+        })
+    }
+
+    /// convert into an owned [TestSet]
+    pub fn into_owned(self) -> TestSet {
+        let mut pdu = TestSet::new(self.vb.into_owned());
+        pdu.header = self.header;
+        pdu.context = context_into_owned(self.context);
+        pdu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::id;
+
+    fn sr() -> SearchRangeList {
+        let mut start = id("1.3.6.1.4.1.23302.1");
+        start.include = 1;
+        SearchRangeList(vec![
+            SearchRange::new(start, id("1.3.6.1.4.1.23302.2")),
+            SearchRange::new(id("1.2.3"), ID::default()),
+        ])
+    }
+
+    #[test]
+    fn idref_prefix() {
+        for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            // 1.3.6.1.4.1.2 with prefix 4
+            let mut b = vec![2, 4, 1, 0];
+            b.extend(&crate::u32_to_bytes(1, &bo));
+            b.extend(&crate::u32_to_bytes(2, &bo));
+
+            let got = IdRef::from_bytes(&b, &bo).unwrap();
+            assert_eq!(got.len(), 7);
+            assert_eq!(got.include(), 1);
+            assert_eq!(got.sub_ids().collect::<Vec<_>>(), vec![1, 3, 6, 1, 4, 1, 2]);
+            assert_eq!(got, id("1.3.6.1.4.1.2"));
+            assert!(got < id("1.3.6.1.4.1.3"));
+            assert!(got > id("1.3.6.1.4.1"));
+
+            let owned = got.into_owned();
+            assert_eq!(owned, ID::from_bytes(&b, &bo).unwrap());
+            assert_eq!(owned.include, 1);
+        }
+
+        // a prefix counts towards the limit
+        let mut b = vec![128, 4, 0, 0];
+        b.extend(vec![0; 128 * 4]);
+        assert_eq!(
+            IdRef::from_bytes(&b, &ByteOrder::LittleEndian).unwrap_err(),
+            ID::from_bytes(&b, &ByteOrder::LittleEndian).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn searchrangeref_contains() {
+        let sr = sr();
+        let bytes = sr.to_bytes(&ByteOrder::LittleEndian);
+        let list = SearchRangeListRef::from_bytes(&bytes, &ByteOrder::LittleEndian).unwrap();
+        assert_eq!(list.len(), 2);
+
+        for (view, owned) in list.iter().zip(&sr) {
+            for i in vec![
+                "1.2.3",
+                "1.2.3.4",
+                "1.3.6.1.4.1.23302.1",
+                "1.3.6.1.4.1.23302.2",
+            ] {
+                assert_eq!(view.contains(&id(i)), owned.contains(&id(i)), "{}", i);
+            }
+        }
+    }
+
+    #[test]
+    fn get_alike_ref() {
+        for nbo in vec![false, true] {
+            let mut get = Get::new(sr());
+            get.header.flags.set_network_byte_order(nbo);
+            get.context = Some(Context(OctetString::from("rck")));
+            let bytes = get.to_bytes().unwrap();
+            let view = GetRef::from_bytes(&bytes).unwrap();
+            assert_eq!(view.context.unwrap().as_str(), Ok("rck"));
+            assert_eq!(view.into_owned(), Get::from_bytes(&bytes).unwrap());
+
+            let mut next = GetNext::new(sr());
+            next.header.flags.set_network_byte_order(nbo);
+            let bytes = next.to_bytes().unwrap();
+            let view = GetNextRef::from_bytes(&bytes).unwrap();
+            assert!(view.context.is_none());
+            assert_eq!(view.into_owned(), GetNext::from_bytes(&bytes).unwrap());
+
+            let mut bulk = GetBulk::new(sr());
+            bulk.header.flags.set_network_byte_order(nbo);
+            bulk.non_repeaters = 1;
+            bulk.max_repetitions = 10;
+            let bytes = bulk.to_bytes().unwrap();
+            let view = GetBulkRef::from_bytes(&bytes).unwrap();
+            assert_eq!(view.max_repetitions, 10);
+            assert_eq!(view.into_owned(), GetBulk::from_bytes(&bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn testset_ref() {
+        let vb = VarBindList(vec![
+            VarBind::new(id("1.2.1"), Value::Integer(-1)),
+            VarBind::new(id("1.2.2"), Value::OctetString(OctetString::from("hello"))),
+            VarBind::new(id("1.2.3"), Value::ObjectIdentifier(id("1.3.6.1.4"))),
+            VarBind::new(id("1.2.4"), Value::Counter64(1 << 40)),
+            VarBind::new(id("1.2.5"), Value::Null),
+            VarBind::new(id("1.2.6"), Value::Opaque(OctetString::from(vec![1, 2]))),
+        ]);
+        let mut testset = TestSet::new(vb);
+        let bytes = testset.to_bytes().unwrap();
+        testset.update_header().unwrap();
+
+        let view = TestSetRef::from_bytes(&bytes).unwrap();
+        assert_eq!(view.vb.len(), 6);
+        let second = view.vb.iter().nth(1).unwrap();
+        assert_eq!(second.name, id("1.2.2"));
+        match second.data {
+            ValueRef::OctetString(s) => assert_eq!(s.as_bytes(), b"hello"),
+            v => panic!("unexpected value: {:?}", v),
+        }
+        assert_eq!(view.into_owned(), testset);
+    }
+
+    #[test]
+    fn ref_errors() {
+        let get = GetNext::new(sr());
+        let mut bytes = get.to_bytes().unwrap();
+        // claim more sub-identifiers than there are in the first start ID
+        bytes[20] = 20;
+        assert_eq!(
+            GetNextRef::from_bytes(&bytes).unwrap_err(),
+            GetNext::from_bytes(&bytes).unwrap_err()
+        );
+
+        let testset = TestSet::new(VarBindList(vec![VarBind::new(id("1.2"), Value::Null)]));
+        let mut bytes = testset.to_bytes().unwrap();
+        bytes[20] = 42;
+        assert_eq!(
+            TestSetRef::from_bytes(&bytes).unwrap_err(),
+            Error::UnknownValueType { offset: 20, ty: 42 }
+        );
+    }
+
+    #[test]
+    fn ref_truncated_padding() {
+        let vbl = VarBindList(vec![VarBind::new(
+            id("1.2"),
+            Value::OctetString(OctetString::from("hello")),
+        )]);
+        let bytes = vbl.to_bytes(&ByteOrder::LittleEndian).unwrap();
+        // cut off the 3 padding bytes of "hello"
+        let bytes = &bytes[..bytes.len() - 3];
+        assert_eq!(
+            VarBindListRef::from_bytes(bytes, &ByteOrder::LittleEndian).unwrap_err(),
+            Error::Truncated {
+                offset: 0,
+                needed: 28,
+                available: 25
+            }
+        );
+    }
+}
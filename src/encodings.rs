@@ -3,6 +3,7 @@
 pub mod context;
 pub mod id;
 pub mod octetstring;
+pub mod oidrange;
 pub mod searchrange;
 pub mod value;
 
@@ -13,6 +14,24 @@ pub use id::ID;
 #[doc(inline)]
 pub use octetstring::OctetString;
 #[doc(inline)]
+pub use oidrange::OidRange;
+#[doc(inline)]
 pub use searchrange::{SearchRange, SearchRangeList};
 #[doc(inline)]
-pub use value::{Value, VarBind, VarBindList};
+pub use value::{Value, ValueError, VarBind, VarBindList};
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{SearchRange, ID};
+    use std::str::FromStr;
+
+    pub(crate) fn id(s: &str) -> ID {
+        ID::from_str(s).unwrap()
+    }
+
+    pub(crate) fn range(start: &str, include: u8, end: &str) -> SearchRange {
+        let mut start = id(start);
+        start.include = include;
+        SearchRange::new(start, id(end))
+    }
+}
@@ -1,7 +1,6 @@
 //! Context as defined in [Section 6.1.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1.1)
 use crate::encodings::OctetString;
-use crate::ByteOrder;
-use std::io::Error;
+use crate::{ByteOrder, Error};
 
 /// Context as defined in [Section 6.1.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1.1)
 ///
@@ -12,7 +11,7 @@ use std::io::Error;
 /// # use agentx::encodings::{Context,OctetString};
 /// # use std::str::FromStr;
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-///   let ctx = Context(OctetString("mystring".to_string()));
+///   let ctx = Context(OctetString::from("mystring"));
 /// # Ok(())
 /// # }
 /// ```
@@ -25,6 +24,11 @@ impl Context {
         self.0.to_bytes(bo)
     }
 
+    /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.encode(bo, buf)
+    }
+
     pub(crate) fn byte_size(&self) -> usize {
         self.0.byte_size()
     }
@@ -41,7 +45,7 @@ mod tests {
 
     #[test]
     fn context_to_bytes_len() {
-        let os = OctetString("rck".to_string());
+        let os = OctetString::from("rck");
         let expected_os_len = 4 + 3 + 1;
         let c = Context(os);
 
@@ -55,7 +59,18 @@ mod tests {
     #[test]
     fn context_serde() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
-            let expected = Context(OctetString("rckx".to_string()));
+            let expected = Context(OctetString::from("rckx"));
+            let bytes = expected.to_bytes(&bo).unwrap();
+            let got = Context::from_bytes(bytes.as_slice(), &bo).unwrap();
+
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn context_serde_binary() {
+        for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let expected = Context(OctetString::from(vec![0xff, 0x00, 0xfe]));
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = Context::from_bytes(bytes.as_slice(), &bo).unwrap();
 
@@ -6,11 +6,14 @@ use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::io::{Error, ErrorKind};
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::str::FromStr;
 
-use crate::{bytes_to_u32, u32_to_bytes, ByteOrder};
+use crate::error::ResultExt;
+use crate::{bytes_to_u32, skip, truncated, u32_to_bytes, ByteOrder, EncodeOptions, Error};
+
+/// maximum number of sub-identifiers in an ID as defined in [Section 5.1](https://datatracker.ietf.org/doc/html/rfc2741#section-5.1)
+pub const MAX_SUB_IDS: usize = 128;
 
 /// OID as defined in [Section 5.1](https://datatracker.ietf.org/doc/html/rfc2741#section-5.1)
 ///
@@ -60,20 +63,152 @@ impl ID {
         self.sub_ids.is_empty()
     }
 
-    /// serialize to bytes
+    /// the (normalized) sub-identifiers
+    pub fn as_slice(&self) -> &[u32] {
+        &self.sub_ids
+    }
+
+    /// iterate over the sub-identifiers
+    pub fn iter(&self) -> std::slice::Iter<'_, u32> {
+        self.sub_ids.iter()
+    }
+
+    /// number of sub-identifiers
+    pub fn len(&self) -> usize {
+        self.sub_ids.len()
+    }
+
+    /// Returns true if the ID has no sub-identifiers, same as [Self::is_null].
+    pub fn is_empty(&self) -> bool {
+        self.sub_ids.is_empty()
+    }
+
+    /// the ID without its last sub-identifier, `None` for the null ID
+    pub fn parent(&self) -> Option<ID> {
+        let (_, parent) = self.sub_ids.split_last()?;
+        Some(Self::from_sub_ids(parent.to_vec()))
+    }
+
+    /// the ID with `sub_id` appended, fails if that exceeds [MAX_SUB_IDS]
+    pub fn child(&self, sub_id: u32) -> Result<ID, Error> {
+        let mut child = Self::from_sub_ids(self.sub_ids.clone());
+        child.append(&[sub_id])?;
+        Ok(child)
+    }
+
+    /// append sub-identifiers, fails without modifying the ID if that exceeds [MAX_SUB_IDS]
+    pub fn append(&mut self, sub_ids: &[u32]) -> Result<(), Error> {
+        let n_subid = self.sub_ids.len() + sub_ids.len();
+        if n_subid > MAX_SUB_IDS {
+            return Err(Error::IdTooLong { offset: 0, n_subid });
+        }
+        self.sub_ids.extend(sub_ids);
+        self.orig_n_subid = n_subid as u8;
+        Ok(())
+    }
+
+    /// Returns true if `prefix` is a prefix of the ID, i.e., the ID is equal to or below `prefix` in the tree.
+    pub fn starts_with(&self, prefix: &ID) -> bool {
+        self.sub_ids.starts_with(&prefix.sub_ids)
+    }
+
+    /// the sub-identifiers following `prefix`, `None` if `prefix` is not a prefix of the ID
+    pub fn strip_prefix(&self, prefix: &ID) -> Option<&[u32]> {
+        if self.starts_with(prefix) {
+            Some(&self.sub_ids[prefix.len()..])
+        } else {
+            None
+        }
+    }
+
+    /// the longest ID that is a prefix of both IDs
+    pub fn common_prefix(&self, other: &ID) -> ID {
+        let n = self
+            .iter()
+            .zip(other.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        Self::from_sub_ids(self.sub_ids[..n].to_vec())
+    }
+
+    /// the next sibling, i.e., the ID with the last sub-identifier incremented. It is the first ID in lexicographical
+    /// order that is not within the subtree of this ID. `None` for the null ID or if the last sub-identifier can not
+    /// be incremented.
+    pub fn next_sibling(&self) -> Option<ID> {
+        let (last, parent) = self.sub_ids.split_last()?;
+        let mut sibling = parent.to_vec();
+        sibling.push(last.checked_add(1)?);
+        Some(Self::from_sub_ids(sibling))
+    }
+
+    /// the lexicographical successor, i.e., the smallest ID that is greater than this ID. `None` if there is no
+    /// greater ID.
+    pub fn successor(&self) -> Option<ID> {
+        if self.len() < MAX_SUB_IDS {
+            return self.child(0).ok();
+        }
+
+        // at maximum length the successor is the next sibling of the closest ancestor that has one
+        let mut id = self.clone();
+        loop {
+            if let Some(sibling) = id.next_sibling() {
+                return Some(sibling);
+            }
+            id = id.parent()?;
+        }
+    }
+
+    // the caller has to make sure that there are at most MAX_SUB_IDS sub-identifiers
+    fn from_sub_ids(sub_ids: Vec<u32>) -> Self {
+        Self {
+            orig_n_subid: sub_ids.len() as u8,
+            include: 0,
+            sub_ids,
+        }
+    }
+
+    /// serialize to bytes, using prefix compression
     pub fn to_bytes(&self, bo: &ByteOrder) -> Vec<u8> {
+        let opts = EncodeOptions::default();
+        let mut result = Vec::with_capacity(self.encoded_size(&opts));
+        self.encode_with(bo, &opts, &mut result);
+        result
+    }
+
+    /// serialize into `buf`, appending to its current contents
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) {
+        self.encode_with(bo, &EncodeOptions::default(), buf)
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents
+    pub fn encode_with(&self, bo: &ByteOrder, opts: &EncodeOptions, buf: &mut Vec<u8>) {
+        let (prefix, sub_ids) = self.compressed(opts);
         // it is the job of the constructor to make sure this assumption holds
         // there is no way one could construct an ID manually that would violate the assumption
-        let n_subid =
-            u8::try_from(self.sub_ids.len()).expect("n_subid lenght checked by constructor");
+        let n_subid = u8::try_from(sub_ids.len()).expect("n_subid lenght checked by constructor");
 
-        // we always normalize IDs, so "prefix" is always 0 in our case
-        let mut result = vec![n_subid, 0, self.include, 0];
-        for id in &self.sub_ids {
-            result.extend(&u32_to_bytes(*id, bo));
+        buf.extend(&[n_subid, prefix, self.include, 0]);
+        for id in sub_ids {
+            buf.extend(&u32_to_bytes(*id, bo));
         }
+    }
 
-        result
+    // the prefix and the sub-identifiers following it on the wire
+    fn compressed(&self, opts: &EncodeOptions) -> (u8, &[u32]) {
+        match self.sub_ids.as_slice() {
+            [1, 3, 6, 1, prefix, rest @ ..]
+                if opts.prefix_compression && *prefix != 0 && *prefix <= u8::MAX as u32 =>
+            {
+                (*prefix as u8, rest)
+            }
+            sub_ids => (0, sub_ids),
+        }
+    }
+
+    // size when serialized with `opts`, unlike byte_size() which is the size of a decoded ID in the original bytes
+    pub(crate) fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        let (_, sub_ids) = self.compressed(opts);
+        size_of::<u32>() /* "header" */ + size_of_val(sub_ids)
     }
 
     pub(crate) fn byte_size(&self) -> usize {
@@ -83,16 +218,25 @@ impl ID {
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
         if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, size_of::<u32>()));
         }
         let n_subid = b[0];
         let prefix = b[1];
         let include = b[2];
         // [3] reserved;
-        let mut b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        // the limit applies to the normalized ID, a prefix expands to 5 more sub-identifiers
+        let normalized = n_subid as usize + if prefix != 0 { 5 } else { 0 };
+        if normalized > MAX_SUB_IDS {
+            return Err(Error::IdTooLong {
+                offset: 0,
+                n_subid: normalized,
+            });
+        }
+        let mut b = skip(b, 4)?;
 
-        if b.len() < n_subid as usize * size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+        let needed = n_subid as usize * size_of::<u32>();
+        if b.len() < needed {
+            return Err(truncated(b, needed)).at(4);
         }
         let mut sub_ids = Vec::with_capacity(n_subid as usize);
         for _ in 0..n_subid {
@@ -139,7 +283,9 @@ impl FromStr for ID {
 
         let mut sub_ids = Vec::new();
         for i in input.split('.') {
-            let val = i.parse::<u32>().map_err(|_| ErrorKind::InvalidData)?;
+            let val = i.parse::<u32>().map_err(|_| Error::InvalidId {
+                input: input.to_string(),
+            })?;
             sub_ids.push(val);
         }
 
@@ -151,12 +297,22 @@ impl TryFrom<Vec<u32>> for ID {
     type Error = Error;
 
     fn try_from(value: Vec<u32>) -> Result<Self, Self::Error> {
-        let orig_n_subid = u8::try_from(value.len()).map_err(|_| ErrorKind::InvalidData)?;
-        Ok(Self {
-            orig_n_subid,
-            include: 0,
-            sub_ids: value,
-        })
+        if value.len() > MAX_SUB_IDS {
+            return Err(Error::IdTooLong {
+                offset: 0,
+                n_subid: value.len(),
+            });
+        }
+        Ok(Self::from_sub_ids(value))
+    }
+}
+
+impl<'a> IntoIterator for &'a ID {
+    type Item = &'a u32;
+    type IntoIter = std::slice::Iter<'a, u32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sub_ids.iter()
     }
 }
 
@@ -228,6 +384,107 @@ mod tests {
         }
     }
 
+    #[test]
+    fn id_prefix_compression() {
+        let bo = ByteOrder::LittleEndian;
+        let compressed = EncodeOptions::default();
+        let uncompressed = EncodeOptions {
+            prefix_compression: false,
+        };
+
+        let id = ID::from_str("1.3.6.1.4.1.23302").unwrap();
+        let bytes = id.to_bytes(&bo);
+        assert_eq!(bytes[..4], [2, 4, 0, 0]);
+        assert_eq!(bytes.len(), id.encoded_size(&compressed));
+        let got = ID::from_bytes(&bytes, &bo).unwrap();
+        assert_eq!(got, id);
+        assert_eq!(got.byte_size(), bytes.len());
+
+        let mut bytes = Vec::new();
+        id.encode_with(&bo, &uncompressed, &mut bytes);
+        assert_eq!(bytes[..4], [7, 0, 0, 0]);
+        assert_eq!(bytes.len(), id.encoded_size(&uncompressed));
+        let got = ID::from_bytes(&bytes, &bo).unwrap();
+        assert_eq!(got, id);
+        assert_eq!(got.byte_size(), bytes.len());
+
+        // re-encoding a decoded ID does not depend on the form it was received in
+        assert_eq!(got.to_bytes(&bo), id.to_bytes(&bo));
+
+        assert_eq!(
+            ID::from_str("1.3.6.1.4").unwrap().to_bytes(&bo)[..2],
+            [0, 4]
+        );
+        for s in &["1.3.6.1", "1.3.6.1.0.1", "1.3.6.1.256.1", "1.3.6.2.4.1"] {
+            let id = ID::from_str(s).unwrap();
+            let bytes = id.to_bytes(&bo);
+            assert_eq!(bytes[1], 0, "{}", s);
+            assert_eq!(bytes.len(), id.encoded_size(&compressed), "{}", s);
+            assert_eq!(ID::from_bytes(&bytes, &bo).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn id_tree_navigation() {
+        let id = |s| ID::from_str(s).unwrap();
+
+        let entry = id("1.3.6.1.2.1.2.2.1");
+        assert_eq!(entry.len(), 9);
+        assert_eq!(entry.as_slice(), &[1, 3, 6, 1, 2, 1, 2, 2, 1]);
+        assert_eq!(entry.iter().sum::<u32>(), 19);
+        assert_eq!((&entry).into_iter().count(), 9);
+
+        let cell = entry.child(2).unwrap().child(7).unwrap();
+        assert_eq!(cell, id("1.3.6.1.2.1.2.2.1.2.7"));
+        assert_eq!(cell.parent(), Some(id("1.3.6.1.2.1.2.2.1.2")));
+        assert_eq!(ID::default().parent(), None);
+
+        let mut row = entry.clone();
+        row.append(&[3, 1, 4]).unwrap();
+        assert_eq!(row, id("1.3.6.1.2.1.2.2.1.3.1.4"));
+        assert!(matches!(
+            row.append(&[0; MAX_SUB_IDS]),
+            Err(Error::IdTooLong { .. })
+        ));
+        assert_eq!(row.len(), 12);
+        assert!(ID::try_from(vec![1; MAX_SUB_IDS])
+            .unwrap()
+            .child(1)
+            .is_err());
+
+        assert!(cell.starts_with(&entry));
+        assert!(cell.starts_with(&cell));
+        assert!(cell.starts_with(&ID::default()));
+        assert!(!entry.starts_with(&cell));
+        assert!(!id("1.3.6.1.2.1.2.2.10").starts_with(&entry));
+        assert_eq!(cell.strip_prefix(&entry), Some(&[2, 7][..]));
+        assert_eq!(cell.strip_prefix(&cell), Some(&[][..]));
+        assert_eq!(entry.strip_prefix(&cell), None);
+
+        assert_eq!(cell.common_prefix(&row), entry);
+        assert_eq!(cell.common_prefix(&id("2.1")), ID::default());
+
+        assert_eq!(cell.next_sibling(), Some(id("1.3.6.1.2.1.2.2.1.2.8")));
+        assert_eq!(id("1.4294967295").next_sibling(), None);
+        assert_eq!(ID::default().next_sibling(), None);
+        assert!(cell.next_sibling().unwrap() > id("1.3.6.1.2.1.2.2.1.2.7.42"));
+
+        assert_eq!(cell.successor(), Some(id("1.3.6.1.2.1.2.2.1.2.7.0")));
+        assert_eq!(ID::default().successor(), Some(id("0")));
+        let mut max = vec![1; MAX_SUB_IDS - 1];
+        max.push(u32::MAX);
+        let max = ID::try_from(max).unwrap();
+        let mut expected = vec![1; MAX_SUB_IDS - 2];
+        expected.push(2);
+        assert_eq!(max.successor(), Some(ID::try_from(expected).unwrap()));
+        assert_eq!(
+            ID::try_from(vec![u32::MAX; MAX_SUB_IDS])
+                .unwrap()
+                .successor(),
+            None
+        );
+    }
+
     #[test]
     fn id_tryfrom() {
         let expected = ID::from_str("1.2.3").unwrap();
@@ -235,4 +492,42 @@ mod tests {
 
         assert_eq!(got, expected);
     }
+
+    #[test]
+    fn id_errors() {
+        assert!(matches!(
+            ID::from_str("1.x.3"),
+            Err(Error::InvalidId { .. })
+        ));
+        assert!(matches!(
+            ID::try_from(vec![1; MAX_SUB_IDS + 1]),
+            Err(Error::IdTooLong { .. })
+        ));
+
+        let bytes = ID::from_str("1.2.3")
+            .unwrap()
+            .to_bytes(&ByteOrder::LittleEndian);
+        assert_eq!(
+            ID::from_bytes(&bytes[..12], &ByteOrder::LittleEndian),
+            Err(Error::Truncated {
+                offset: 4,
+                needed: 12,
+                available: 8
+            })
+        );
+
+        // a prefix counts towards the limit
+        let mut bytes = vec![MAX_SUB_IDS as u8, 2, 0, 0];
+        bytes.extend(vec![0; MAX_SUB_IDS * 4]);
+        assert_eq!(
+            ID::from_bytes(&bytes, &ByteOrder::LittleEndian),
+            Err(Error::IdTooLong {
+                offset: 0,
+                n_subid: MAX_SUB_IDS + 5
+            })
+        );
+        bytes[0] = (MAX_SUB_IDS - 5) as u8;
+        let id = ID::from_bytes(&bytes, &ByteOrder::LittleEndian).unwrap();
+        assert_eq!(id.len(), MAX_SUB_IDS);
+    }
 }
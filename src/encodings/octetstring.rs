@@ -1,11 +1,17 @@
 //! Octet String as defined in [Section 5.3](https://datatracker.ietf.org/doc/html/rfc2741#section-5.3)
+//!
+//! Octet Strings are binary safe, they are not required to contain valid UTF-8. Lossless views are provided for
+//! the cases where the octets actually are a valid UTF-8 string (e.g., a DisplayString).
 
+use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fmt;
-use std::io::{Error, ErrorKind};
 use std::mem::size_of;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
 
-use crate::{bytes_to_u32, u32_to_bytes, ByteOrder};
+use crate::error::ResultExt;
+use crate::{bytes_to_u32, skip, truncated, u32_to_bytes, ByteOrder, Error};
 
 /// Octet String as defined in [Section 5.3](https://datatracker.ietf.org/doc/html/rfc2741#section-5.3)
 ///
@@ -16,37 +22,78 @@ use crate::{bytes_to_u32, u32_to_bytes, ByteOrder};
 /// # use agentx::encodings::OctetString;
 /// # use std::str::FromStr;
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-///   let os = OctetString("mystring".to_string());
+///   let os = OctetString::from("mystring");
+///   assert_eq!(os.as_str()?, "mystring");
+///
+///   let mac = OctetString::from(vec![0x00, 0x16, 0x3e, 0xff, 0xfe, 0x01]);
+///   assert!(mac.as_str().is_err());
 /// # Ok(())
 /// # }
 /// ```
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
-pub struct OctetString(pub String);
+pub struct OctetString(pub Vec<u8>);
 // length: u32
 // octets: Vec(u8)
 
 impl OctetString {
-    /// serialize to bytes
-    pub fn to_bytes(&self, bo: &ByteOrder) -> Result<Vec<u8>, Error> {
-        let mut content: Vec<u8> = self.0.as_bytes().to_vec();
+    /// create an OctetString from anything that can be turned into octets
+    pub fn new<T: Into<Vec<u8>>>(octets: T) -> Self {
+        Self(octets.into())
+    }
 
-        let orig_len = content.len();
-        while content.len() % 4 != 0 {
-            content.push(0);
-        }
+    /// raw octets
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// the octets as `&str`, fails if they are not valid UTF-8
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.0)
+    }
+
+    /// the octets as string, invalid UTF-8 sequences are replaced by `U+FFFD`
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
 
-        let len = u32::try_from(orig_len).map_err(|_| ErrorKind::InvalidData)?;
-        let len = u32_to_bytes(len, bo);
+    /// consume the OctetString and return the raw octets
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
 
-        let mut result = Vec::new();
-        result.extend(&len);
-        result.extend(content);
+    /// Returns the number of octets (without padding)
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the OctetString contains no octets.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 
+    /// serialize to bytes
+    pub fn to_bytes(&self, bo: &ByteOrder) -> Result<Vec<u8>, Error> {
+        let mut result = Vec::with_capacity(self.byte_size());
+        self.encode(bo, &mut result)?;
         Ok(result)
     }
 
+    /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let len = u32::try_from(self.0.len()).map_err(|_| Error::ValueOutOfRange {
+            field: "octet string length",
+        })?;
+
+        buf.extend(&u32_to_bytes(len, bo));
+        buf.extend(&self.0);
+        let padding = self.byte_size() - size_of::<u32>() - self.0.len();
+        buf.extend(&[0, 0, 0][..padding]);
+
+        Ok(())
+    }
+
     pub(crate) fn byte_size(&self) -> usize {
-        let mut octets_len = self.0.as_bytes().len();
+        let mut octets_len = self.0.len();
         while octets_len % 4 != 0 {
             octets_len += 1;
         }
@@ -56,27 +103,70 @@ impl OctetString {
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
-        }
-        let length = bytes_to_u32(b, bo)?;
+        let length = bytes_to_u32(b, bo)? as usize;
         if length == 0 {
-            return Ok(OctetString("".to_string()));
+            return Ok(OctetString(Vec::new()));
         }
 
         // the length is the the actual string lenght *without* padding
+        let b = skip(b, size_of::<u32>())?;
         let b = b
-            .get(4..4 + length as usize)
-            .ok_or(ErrorKind::InvalidData)?;
-        let string = String::from_utf8(b.to_vec()).map_err(|_| ErrorKind::InvalidData)?;
+            .get(..length)
+            .ok_or_else(|| truncated(b, length))
+            .at(size_of::<u32>())?;
 
-        Ok(OctetString(string))
+        Ok(OctetString(b.to_vec()))
     }
 }
 
+// lossy, use as_str() if you need to know if the octets are valid UTF-8
 impl fmt::Display for OctetString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.to_string_lossy())
+    }
+}
+
+impl From<Vec<u8>> for OctetString {
+    fn from(octets: Vec<u8>) -> Self {
+        Self(octets)
+    }
+}
+
+impl From<&[u8]> for OctetString {
+    fn from(octets: &[u8]) -> Self {
+        Self(octets.to_vec())
+    }
+}
+
+impl From<String> for OctetString {
+    fn from(s: String) -> Self {
+        Self(s.into_bytes())
+    }
+}
+
+impl From<&str> for OctetString {
+    fn from(s: &str) -> Self {
+        Self(s.as_bytes().to_vec())
+    }
+}
+
+impl From<OctetString> for Vec<u8> {
+    fn from(os: OctetString) -> Self {
+        os.0
+    }
+}
+
+impl TryFrom<OctetString> for String {
+    type Error = FromUtf8Error;
+
+    fn try_from(os: OctetString) -> Result<Self, Self::Error> {
+        String::from_utf8(os.0)
+    }
+}
+
+impl AsRef<[u8]> for OctetString {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
 }
 
@@ -86,19 +176,19 @@ mod tests {
 
     #[test]
     fn octet_to_bytes_len_manual() {
-        let os = OctetString("rck".to_string());
+        let os = OctetString::from("rck");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             4 + 3 /* chars */ + 1 /* padding */
         );
 
-        let os = OctetString("rckx".to_string());
+        let os = OctetString::from("rckx");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             4 + 4 /* chars, no padding */
         );
 
-        let os = OctetString("".to_string());
+        let os = OctetString::from("");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             4 /* lenght only */
@@ -107,19 +197,19 @@ mod tests {
 
     #[test]
     fn octet_to_bytes_len() {
-        let os = OctetString("rck".to_string());
+        let os = OctetString::from("rck");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             os.byte_size()
         );
 
-        let os = OctetString("rckx".to_string());
+        let os = OctetString::from("rckx");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             os.byte_size()
         );
 
-        let os = OctetString("".to_string());
+        let os = OctetString::from("");
         assert_eq!(
             os.to_bytes(&ByteOrder::LittleEndian).unwrap().len(),
             os.byte_size()
@@ -130,22 +220,45 @@ mod tests {
     fn octet_serde() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
             // aligned
-            let expected = OctetString("rckx".to_string());
+            let expected = OctetString::from("rckx");
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = OctetString::from_bytes(bytes.as_slice(), &bo).unwrap();
             assert_eq!(expected, got);
 
             // not aligned
-            let expected = OctetString("rckxy".to_string());
+            let expected = OctetString::from("rckxy");
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = OctetString::from_bytes(bytes.as_slice(), &bo).unwrap();
             assert_eq!(expected, got);
 
             // zero
-            let expected = OctetString("".to_string());
+            let expected = OctetString::from("");
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = OctetString::from_bytes(bytes.as_slice(), &bo).unwrap();
             assert_eq!(expected, got);
         }
     }
+
+    #[test]
+    fn octet_serde_binary() {
+        for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let expected = OctetString::from(vec![0x00, 0x16, 0x3e, 0xff, 0xfe]);
+            let bytes = expected.to_bytes(&bo).unwrap();
+            let got = OctetString::from_bytes(bytes.as_slice(), &bo).unwrap();
+            assert_eq!(expected, got);
+            assert!(got.as_str().is_err());
+        }
+    }
+
+    #[test]
+    fn octet_utf8_views() {
+        let os = OctetString::from("rck");
+        assert_eq!(os.as_str().unwrap(), "rck");
+        assert_eq!(String::try_from(os.clone()).unwrap(), "rck".to_string());
+        assert_eq!(os.to_string(), "rck");
+
+        let os = OctetString::from(vec![b'r', 0xff]);
+        assert_eq!(os.to_string_lossy(), "r\u{fffd}");
+        assert!(String::try_from(os).is_err());
+    }
 }
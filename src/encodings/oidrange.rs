@@ -0,0 +1,332 @@
+//! Regions of range registrations as defined in [Section 6.2.3](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.3)
+//!
+//! A Register PDU with a non-zero `range_subid` does not register a single subtree, but one subtree for every value
+//! from the sub-identifier at position `range_subid` of `subtree` up to `upper_bound`. For example `subtree`
+//! `1.3.6.1.2.1.2.2.1.1`, `range_subid` 10 and `upper_bound` 22 register all columns of the ifTable, written as
+//! `1.3.6.1.2.1.2.2.1.[1-22]`.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::encodings::ID;
+use crate::Error;
+
+/// A registered region, either a single subtree or a range of subtrees
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{OidRange, ID};
+/// # use std::str::FromStr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let columns = OidRange::new(ID::from_str("1.3.6.1.2.1.2.2.1.1")?, 10, 22)?;
+/// assert_eq!(columns.to_string(), "1.3.6.1.2.1.2.2.1.[1-22]");
+/// assert!(columns.contains(&ID::from_str("1.3.6.1.2.1.2.2.1.5.3")?));
+/// assert!(!columns.contains(&ID::from_str("1.3.6.1.2.1.2.2.1.23.3")?));
+/// assert_eq!(columns.subtrees().count(), 22);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OidRange {
+    base: ID,
+    range_subid: u8,
+    upper_bound: Option<u32>,
+}
+
+impl OidRange {
+    /// the range of subtrees that differ from `base` only in the sub-identifier at the (1 based) position
+    /// `range_subid`, which goes from its value in `base` up to `upper_bound`. Fails if `range_subid` is 0 or
+    /// outside of `base`, or if `upper_bound` is less than the lower bound.
+    pub fn new(mut base: ID, range_subid: u8, upper_bound: u32) -> Result<Self, Error> {
+        let valid = range_subid != 0
+            && base
+                .as_slice()
+                .get(range_subid as usize - 1)
+                .map_or(false, |lower| *lower <= upper_bound);
+        if !valid {
+            return Err(Error::InvalidRange {
+                range_subid,
+                upper_bound: Some(upper_bound),
+            });
+        }
+
+        base.include = 0;
+        Ok(Self {
+            base,
+            range_subid,
+            upper_bound: Some(upper_bound),
+        })
+    }
+
+    /// the single subtree `base`
+    pub fn subtree(mut base: ID) -> Self {
+        base.include = 0;
+        Self {
+            base,
+            range_subid: 0,
+            upper_bound: None,
+        }
+    }
+
+    /// the region described by the fields of a Register or Unregister PDU. `upper_bound` has to be present exactly
+    /// when `range_subid` is not 0.
+    pub fn from_parts(base: ID, range_subid: u8, upper_bound: Option<u32>) -> Result<Self, Error> {
+        match (range_subid, upper_bound) {
+            (0, None) => Ok(Self::subtree(base)),
+            (r, Some(u)) if r != 0 => Self::new(base, r, u),
+            _ => Err(Error::InvalidRange {
+                range_subid,
+                upper_bound,
+            }),
+        }
+    }
+
+    /// the subtree OID as sent in the Register PDU, its sub-identifier at `range_subid` is the lower bound
+    pub fn base(&self) -> &ID {
+        &self.base
+    }
+
+    /// (1 based) position of the range sub-identifier, 0 for a single subtree
+    pub fn range_subid(&self) -> u8 {
+        self.range_subid
+    }
+
+    /// upper bound of the range sub-identifier, `None` for a single subtree
+    pub fn upper_bound(&self) -> Option<u32> {
+        self.upper_bound
+    }
+
+    /// lower bound of the range sub-identifier, `None` for a single subtree
+    pub fn lower_bound(&self) -> Option<u32> {
+        self.upper_bound.map(|_| self.base.as_slice()[self.pos()])
+    }
+
+    /// Returns true if the region is a range of subtrees.
+    pub fn is_range(&self) -> bool {
+        self.range_subid != 0
+    }
+
+    // 0 based position of the range sub-identifier, only meaningful for ranges
+    fn pos(&self) -> usize {
+        self.range_subid as usize - 1
+    }
+
+    fn with_subid(&self, sub_id: u32) -> ID {
+        let mut sub_ids = self.base.as_slice().to_vec();
+        sub_ids[self.pos()] = sub_id;
+        ID::try_from(sub_ids).expect("same length as base")
+    }
+
+    /// the lexicographically first subtree of the region
+    pub fn first(&self) -> ID {
+        self.base.clone()
+    }
+
+    /// the lexicographically last subtree of the region
+    pub fn last(&self) -> ID {
+        match self.upper_bound {
+            Some(upper_bound) => self.with_subid(upper_bound),
+            None => self.base.clone(),
+        }
+    }
+
+    /// Returns true if `id` is equal to or below one of the subtrees of the region.
+    pub fn contains(&self, id: &ID) -> bool {
+        let (base, id) = (self.base.as_slice(), id.as_slice());
+        if id.len() < base.len() {
+            return false;
+        }
+
+        match (self.lower_bound(), self.upper_bound) {
+            (Some(lower), Some(upper)) => {
+                let pos = self.pos();
+                id[..pos] == base[..pos]
+                    && (lower..=upper).contains(&id[pos])
+                    && id[pos + 1..base.len()] == base[pos + 1..]
+            }
+            _ => id.starts_with(base),
+        }
+    }
+
+    /// all subtrees of the region in lexicographical order
+    pub fn subtrees(&self) -> Subtrees<'_> {
+        Subtrees {
+            range: self,
+            next: Some(self.lower_bound().unwrap_or(0)),
+        }
+    }
+
+    /// like [OidRange::subtrees], but skips the subtrees that are before `id` already in the sub-identifiers up to
+    /// the range sub-identifier, i.e., it starts at the subtree `id` is in or before
+    pub fn subtrees_from(&self, id: &ID) -> Subtrees<'_> {
+        let (lower, upper) = match (self.lower_bound(), self.upper_bound) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => return self.subtrees(),
+        };
+
+        let (pos, base, id) = (self.pos(), self.base.as_slice(), id.as_slice());
+        let next = match id[..pos.min(id.len())].cmp(&base[..pos]) {
+            Ordering::Less => Some(lower),
+            Ordering::Greater => None,
+            Ordering::Equal => match id.get(pos) {
+                None => Some(lower),
+                Some(sub_id) if *sub_id > upper => None,
+                Some(sub_id) => Some(lower.max(*sub_id)),
+            },
+        };
+        Subtrees { range: self, next }
+    }
+}
+
+impl From<ID> for OidRange {
+    fn from(base: ID) -> Self {
+        Self::subtree(base)
+    }
+}
+
+impl fmt::Display for OidRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lower, upper) = match (self.lower_bound(), self.upper_bound) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => return write!(f, "{}", self.base),
+        };
+
+        for (i, sub_id) in self.base.iter().enumerate() {
+            if i != 0 {
+                write!(f, ".")?;
+            }
+            if i == self.pos() {
+                write!(f, "[{}-{}]", lower, upper)?;
+            } else {
+                write!(f, "{}", sub_id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the subtrees of an [OidRange]
+#[derive(Clone, Debug)]
+pub struct Subtrees<'a> {
+    range: &'a OidRange,
+    next: Option<u32>,
+}
+
+impl<'a> Iterator for Subtrees<'a> {
+    type Item = ID;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sub_id = self.next?;
+        match self.range.upper_bound {
+            Some(upper_bound) => {
+                self.next = sub_id.checked_add(1).filter(|n| *n <= upper_bound);
+                Some(self.range.with_subid(sub_id))
+            }
+            None => {
+                self.next = None;
+                Some(self.range.base.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::id;
+
+    #[test]
+    fn oidrange_new() {
+        let r = OidRange::new(id("1.2.3.4"), 3, 5).unwrap();
+        assert_eq!(r.lower_bound(), Some(3));
+        assert_eq!(r.upper_bound(), Some(5));
+        assert_eq!(r.first(), id("1.2.3.4"));
+        assert_eq!(r.last(), id("1.2.5.4"));
+        assert_eq!(r.to_string(), "1.2.[3-5].4");
+        assert!(r.is_range());
+
+        let invalid = |range_subid, upper_bound| {
+            Err(Error::InvalidRange {
+                range_subid,
+                upper_bound,
+            })
+        };
+        assert_eq!(OidRange::new(id("1.2.3.4"), 0, 5), invalid(0, Some(5)));
+        assert_eq!(OidRange::new(id("1.2.3.4"), 5, 5), invalid(5, Some(5)));
+        assert_eq!(OidRange::new(id("1.2.3.4"), 3, 2), invalid(3, Some(2)));
+
+        assert_eq!(
+            OidRange::from_parts(id("1.2"), 0, None),
+            Ok(OidRange::subtree(id("1.2")))
+        );
+        assert_eq!(
+            OidRange::from_parts(id("1.2"), 2, Some(3)).unwrap().last(),
+            id("1.3")
+        );
+        assert_eq!(
+            OidRange::from_parts(id("1.2"), 0, Some(3)),
+            invalid(0, Some(3))
+        );
+        assert_eq!(OidRange::from_parts(id("1.2"), 2, None), invalid(2, None));
+    }
+
+    #[test]
+    fn oidrange_contains() {
+        let r = OidRange::new(id("1.2.3.4"), 3, 5).unwrap();
+        assert!(r.contains(&id("1.2.3.4")));
+        assert!(r.contains(&id("1.2.4.4.1")));
+        assert!(r.contains(&id("1.2.5.4")));
+        assert!(!r.contains(&id("1.2.6.4")));
+        assert!(!r.contains(&id("1.2.2.4")));
+        assert!(!r.contains(&id("1.2.4.5")));
+        assert!(!r.contains(&id("1.2.4")));
+
+        let s = OidRange::subtree(id("1.2"));
+        assert!(s.contains(&id("1.2")));
+        assert!(s.contains(&id("1.2.7")));
+        assert!(!s.contains(&id("1.3")));
+        assert_eq!(s.to_string(), "1.2");
+    }
+
+    #[test]
+    fn oidrange_subtrees() {
+        let r = OidRange::new(id("1.2.3.4"), 3, 5).unwrap();
+        assert_eq!(
+            r.subtrees().collect::<Vec<_>>(),
+            vec![id("1.2.3.4"), id("1.2.4.4"), id("1.2.5.4")]
+        );
+
+        let r = OidRange::new(id("1.4294967295"), 2, u32::MAX).unwrap();
+        assert_eq!(r.subtrees().collect::<Vec<_>>(), vec![id("1.4294967295")]);
+
+        let s = OidRange::subtree(id("1.2"));
+        assert_eq!(s.subtrees().collect::<Vec<_>>(), vec![id("1.2")]);
+    }
+
+    #[test]
+    fn oidrange_subtrees_from() {
+        let r = OidRange::new(id("1.2.3.4"), 3, 5).unwrap();
+        let from = |s| r.subtrees_from(&id(s)).collect::<Vec<_>>();
+        assert_eq!(from("1.2.4.9"), vec![id("1.2.4.4"), id("1.2.5.4")]);
+        assert_eq!(from("1.2.5"), vec![id("1.2.5.4")]);
+        assert_eq!(from("1.2.1.7"), r.subtrees().collect::<Vec<_>>());
+        assert_eq!(from("1.1.9"), r.subtrees().collect::<Vec<_>>());
+        assert_eq!(from("1"), r.subtrees().collect::<Vec<_>>());
+        assert_eq!(from("1.2.6"), vec![]);
+        assert_eq!(from("1.3"), vec![]);
+
+        let r = OidRange::new(id("1.0.1"), 2, u32::MAX).unwrap();
+        assert_eq!(
+            r.subtrees_from(&id("1.4294967294.7")).collect::<Vec<_>>(),
+            vec![id("1.4294967294.1"), id("1.4294967295.1")]
+        );
+
+        let s = OidRange::subtree(id("1.2"));
+        assert_eq!(
+            s.subtrees_from(&id("1.3")).collect::<Vec<_>>(),
+            vec![id("1.2")]
+        );
+    }
+}
@@ -1,10 +1,10 @@
 //! SearchRange (and SearchRangeList) as defined in [Section 5.2](https://datatracker.ietf.org/doc/html/rfc2741#section-5.2)
 
-use std::io::{Error, ErrorKind};
 use std::iter::IntoIterator;
 
 use crate::encodings::ID;
-use crate::ByteOrder;
+use crate::error::ResultExt;
+use crate::{skip, ByteOrder, EncodeOptions, Error};
 
 /// SearchRange as defined in [Section 5.2](https://datatracker.ietf.org/doc/html/rfc2741#section-5.2)
 ///
@@ -35,20 +35,43 @@ impl SearchRange {
         Self { start, end }
     }
 
+    /// check if an ID is within the range. It has to be greater than `start` (or equal if `start.include` is set)
+    /// and less than `end`, unless `end` is the null ID.
+    pub fn contains(&self, id: &ID) -> bool {
+        let after_start = *id > self.start || (self.start.include != 0 && *id == self.start);
+        let before_end = self.end.is_null() || *id < self.end;
+
+        after_start && before_end
+    }
+
     /// serialize to bytes
     pub fn to_bytes(&self, bo: &ByteOrder) -> Vec<u8> {
-        let mut result: Vec<u8> = self.start.to_bytes(bo);
-        result.extend(self.end.to_bytes(bo));
-
+        let opts = EncodeOptions::default();
+        let mut result = Vec::with_capacity(self.encoded_size(&opts));
+        self.encode_with(bo, &opts, &mut result);
         result
     }
 
+    /// serialize into `buf`, appending to its current contents
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) {
+        self.encode_with(bo, &EncodeOptions::default(), buf)
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents
+    pub fn encode_with(&self, bo: &ByteOrder, opts: &EncodeOptions, buf: &mut Vec<u8>) {
+        self.start.encode_with(bo, opts, buf);
+        self.end.encode_with(bo, opts, buf);
+    }
+
+    pub(crate) fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        self.start.encoded_size(opts) + self.end.encoded_size(opts)
+    }
+
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        let mut b = b;
         let start = ID::from_bytes(b, bo)?;
-        b = b.get(start.byte_size()..).ok_or(ErrorKind::InvalidData)?;
-        let end = ID::from_bytes(b, bo)?;
+        let offset = start.byte_size();
+        let end = ID::from_bytes(skip(b, offset)?, bo).at(offset)?;
 
         Ok(SearchRange { start, end })
     }
@@ -65,23 +88,39 @@ pub struct SearchRangeList(pub Vec<SearchRange>);
 impl SearchRangeList {
     /// serialize to bytes
     pub fn to_bytes(&self, bo: &ByteOrder) -> Vec<u8> {
-        let mut result = Vec::new();
+        let opts = EncodeOptions::default();
+        let mut result = Vec::with_capacity(self.encoded_size(&opts));
+        self.encode_with(bo, &opts, &mut result);
+        result
+    }
 
+    /// serialize into `buf`, appending to its current contents
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) {
+        self.encode_with(bo, &EncodeOptions::default(), buf)
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents
+    pub fn encode_with(&self, bo: &ByteOrder, opts: &EncodeOptions, buf: &mut Vec<u8>) {
         for r in &self.0 {
-            result.extend(r.to_bytes(bo));
+            r.encode_with(bo, opts, buf);
         }
+    }
 
-        result
+    pub(crate) fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        self.0.iter().map(|r| r.encoded_size(opts)).sum()
     }
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        let mut b = b;
+        let mut offset = 0;
         let mut ranges = Vec::new();
 
-        while !b.is_empty() {
-            let sr = SearchRange::from_bytes(b, bo)?;
-            b = b.get(sr.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        while offset < b.len() {
+            let rest = &b[offset..];
+            let sr = SearchRange::from_bytes(rest, bo).at(offset)?;
+            // the last element has to be complete, including its padding
+            skip(rest, sr.byte_size()).at(offset)?;
+            offset += sr.byte_size();
             ranges.push(sr);
         }
 
@@ -149,6 +188,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn searchrange_contains() {
+        let mut start = ID::from_str("1.2.3").unwrap();
+        let end = ID::from_str("1.2.5").unwrap();
+        let id = |s| ID::from_str(s).unwrap();
+
+        let sr = SearchRange::new(start.clone(), end.clone());
+        assert!(!sr.contains(&id("1.2.3")));
+        assert!(sr.contains(&id("1.2.3.0")));
+        assert!(sr.contains(&id("1.2.4.1")));
+        assert!(!sr.contains(&id("1.2.5")));
+        assert!(!sr.contains(&id("1.2")));
+
+        start.include = 1;
+        let sr = SearchRange::new(start.clone(), end);
+        assert!(sr.contains(&id("1.2.3")));
+
+        let sr = SearchRange::new(start, ID::default());
+        assert!(sr.contains(&id("1.3")));
+    }
+
     #[test]
     fn searchrangelist_serde() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
@@ -1,14 +1,19 @@
 //! Value and VarBind as defined in [Section 5.4](https://datatracker.ietf.org/doc/html/rfc2741#section-5.4)
 
-use std::io::{Error, ErrorKind};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
 use std::iter::IntoIterator;
 use std::mem::size_of;
+use std::net::Ipv4Addr;
+use std::time::Duration;
 
 use crate::encodings::OctetString;
 use crate::encodings::ID;
+use crate::error::ResultExt;
 use crate::{
-    bytes_to_i32, bytes_to_u16, bytes_to_u32, bytes_to_u64, i32_to_bytes, u16_to_bytes,
-    u32_to_bytes, u64_to_bytes, ByteOrder,
+    bytes_to_i32, bytes_to_u16, bytes_to_u32, bytes_to_u64, i32_to_bytes, skip, u16_to_bytes,
+    u32_to_bytes, u64_to_bytes, ByteOrder, EncodeOptions, Error,
 };
 
 /// VarBind as defined in [Section 5.4](https://datatracker.ietf.org/doc/html/rfc2741#section-5.4)
@@ -62,13 +67,22 @@ pub enum Value {
 
 impl Value {
     fn byte_size(&self) -> usize {
+        self.size(ID::byte_size)
+    }
+
+    fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        self.size(|i| i.encoded_size(opts))
+    }
+
+    // only the size of IDs depends on whether they are decoded or serialized
+    fn size<F: Fn(&ID) -> usize>(&self, id_size: F) -> usize {
         // type + reserved + ..;
         size_of::<u32>()
             + match self {
                 Self::Integer(_) => size_of::<i32>(),
                 Self::OctetString(o) => o.byte_size(),
                 Self::Null => 0,
-                Self::ObjectIdentifier(i) => i.byte_size(),
+                Self::ObjectIdentifier(i) => id_size(i),
                 Self::IpAddress(s) => s.byte_size(),
                 Self::Counter32(_) => size_of::<u32>(),
                 Self::Gauge32(_) => size_of::<u32>(),
@@ -82,6 +96,238 @@ impl Value {
     }
 }
 
+/// TruthValue `true` as defined in [RFC 2579](https://datatracker.ietf.org/doc/html/rfc2579)
+pub const TRUTH_VALUE_TRUE: i32 = 1;
+/// TruthValue `false` as defined in [RFC 2579](https://datatracker.ietf.org/doc/html/rfc2579)
+pub const TRUTH_VALUE_FALSE: i32 = 2;
+
+/// Error returned by the typed extractors of [Value]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ValueError {
+    /// the Value is not of the expected variant
+    WrongType {
+        /// name of the expected variant
+        expected: &'static str,
+        /// name of the actual variant, see [Value::type_name]
+        actual: &'static str,
+    },
+    /// the value is not valid for the expected type, e.g., an IpAddress that does not have 4 octets, a TruthValue
+    /// other than 1 or 2, or a Duration that exceeds TimeTicks
+    Invalid {
+        /// name of the expected type
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongType { expected, actual } => {
+                write!(f, "expected {} value, got {}", expected, actual)
+            }
+            Self::Invalid { expected } => write!(f, "not a valid {} value", expected),
+        }
+    }
+}
+
+impl error::Error for ValueError {}
+
+impl Value {
+    /// name of the variant, e.g. `"Integer"`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Integer(_) => "Integer",
+            Self::OctetString(_) => "OctetString",
+            Self::Null => "Null",
+            Self::ObjectIdentifier(_) => "ObjectIdentifier",
+            Self::IpAddress(_) => "IpAddress",
+            Self::Counter32(_) => "Counter32",
+            Self::Gauge32(_) => "Gauge32",
+            Self::TimeTicks(_) => "TimeTicks",
+            Self::Opaque(_) => "Opaque",
+            Self::Counter64(_) => "Counter64",
+            Self::NoSuchObject => "NoSuchObject",
+            Self::NoSuchInstance => "NoSuchInstance",
+            Self::EndOfMibView => "EndOfMibView",
+        }
+    }
+
+    fn wrong_type(&self, expected: &'static str) -> ValueError {
+        ValueError::WrongType {
+            expected,
+            actual: self.type_name(),
+        }
+    }
+
+    /// TruthValue as Integer, 1 for `true` and 2 for `false`
+    pub fn truth_value(value: bool) -> Self {
+        Self::Integer(if value {
+            TRUTH_VALUE_TRUE
+        } else {
+            TRUTH_VALUE_FALSE
+        })
+    }
+
+    /// TimeTicks in 100th of seconds, fails if `duration` exceeds the unsigned 32 bit range of TimeTicks
+    pub fn time_ticks(duration: Duration) -> Result<Self, ValueError> {
+        u32::try_from(duration.as_millis() / 10)
+            // stored in the bits of the i32, see [Self::as_duration]
+            .map(|ticks| Self::TimeTicks(ticks as i32))
+            .map_err(|_| ValueError::Invalid {
+                expected: "TimeTicks",
+            })
+    }
+
+    /// the value of an Integer
+    pub fn as_i32(&self) -> Result<i32, ValueError> {
+        match self {
+            Self::Integer(i) => Ok(*i),
+            _ => Err(self.wrong_type("Integer")),
+        }
+    }
+
+    /// the value of a Counter32
+    pub fn as_counter32(&self) -> Result<u32, ValueError> {
+        match self {
+            Self::Counter32(c) => Ok(*c),
+            _ => Err(self.wrong_type("Counter32")),
+        }
+    }
+
+    /// the value of a Gauge32
+    pub fn as_gauge32(&self) -> Result<u32, ValueError> {
+        match self {
+            Self::Gauge32(g) => Ok(*g),
+            _ => Err(self.wrong_type("Gauge32")),
+        }
+    }
+
+    /// the value of a Counter64
+    pub fn as_u64(&self) -> Result<u64, ValueError> {
+        match self {
+            Self::Counter64(c) => Ok(*c),
+            _ => Err(self.wrong_type("Counter64")),
+        }
+    }
+
+    /// TimeTicks as Duration. TimeTicks are unsigned, the sign bit is read as part of the value.
+    pub fn as_duration(&self) -> Result<Duration, ValueError> {
+        match self {
+            Self::TimeTicks(t) => Ok(Duration::from_millis(*t as u32 as u64 * 10)),
+            _ => Err(self.wrong_type("TimeTicks")),
+        }
+    }
+
+    /// the octets of an OctetString
+    pub fn as_bytes(&self) -> Result<&[u8], ValueError> {
+        match self {
+            Self::OctetString(s) => Ok(s.as_bytes()),
+            _ => Err(self.wrong_type("OctetString")),
+        }
+    }
+
+    /// the OID of an ObjectIdentifier
+    pub fn as_id(&self) -> Result<&ID, ValueError> {
+        match self {
+            Self::ObjectIdentifier(id) => Ok(id),
+            _ => Err(self.wrong_type("ObjectIdentifier")),
+        }
+    }
+
+    /// the address of an IpAddress, fails if it does not have 4 octets
+    pub fn as_ipv4(&self) -> Result<Ipv4Addr, ValueError> {
+        match self {
+            Self::IpAddress(a) => <[u8; 4]>::try_from(a.as_bytes())
+                .map(Ipv4Addr::from)
+                .map_err(|_| ValueError::Invalid {
+                    expected: "IpAddress",
+                }),
+            _ => Err(self.wrong_type("IpAddress")),
+        }
+    }
+
+    /// the value of a TruthValue, fails if the Integer is neither 1 nor 2
+    pub fn as_bool(&self) -> Result<bool, ValueError> {
+        match self.as_i32() {
+            Ok(TRUTH_VALUE_TRUE) => Ok(true),
+            Ok(TRUTH_VALUE_FALSE) => Ok(false),
+            Ok(_) => Err(ValueError::Invalid {
+                expected: "TruthValue",
+            }),
+            Err(_) => Err(self.wrong_type("TruthValue")),
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Self::Integer(i)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(c: u64) -> Self {
+        Self::Counter64(c)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::truth_value(b)
+    }
+}
+
+impl From<Ipv4Addr> for Value {
+    fn from(a: Ipv4Addr) -> Self {
+        Self::IpAddress(OctetString::from(&a.octets()[..]))
+    }
+}
+
+impl From<OctetString> for Value {
+    fn from(s: OctetString) -> Self {
+        Self::OctetString(s)
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(s: &[u8]) -> Self {
+        Self::OctetString(OctetString::from(s))
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(s: Vec<u8>) -> Self {
+        Self::OctetString(OctetString::from(s))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::OctetString(OctetString::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::OctetString(OctetString::from(s))
+    }
+}
+
+impl From<ID> for Value {
+    fn from(id: ID) -> Self {
+        Self::ObjectIdentifier(id)
+    }
+}
+
+impl TryFrom<Duration> for Value {
+    type Error = ValueError;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        Self::time_ticks(duration)
+    }
+}
+
 impl VarBind {
     /// create a VarBind from an OID and a value
     pub fn new(name: ID, data: Value) -> Self {
@@ -90,77 +336,105 @@ impl VarBind {
 
     /// serialize to bytes
     pub fn to_bytes(&self, bo: &ByteOrder) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-
-        let (ty, data) = match &self.data {
-            Value::Integer(i) => (2, i32_to_bytes(*i, bo).to_vec()),
-            Value::OctetString(s) => (4, s.to_bytes(bo)?),
-            Value::Null => (5, vec![]),
-            Value::ObjectIdentifier(i) => (6, i.to_bytes(bo)),
-            Value::IpAddress(a) => (64, a.to_bytes(bo)?),
-            Value::Counter32(c) => (65, u32_to_bytes(*c, bo).to_vec()),
-            Value::Gauge32(g) => (66, u32_to_bytes(*g, bo).to_vec()),
-            Value::TimeTicks(t) => (67, i32_to_bytes(*t, bo).to_vec()),
-            Value::Opaque(o) => (68, o.to_bytes(bo)?),
-            Value::Counter64(c) => (70, u64_to_bytes(*c, bo).to_vec()),
-            Value::NoSuchObject => (128, vec![]),
-            Value::NoSuchInstance => (129, vec![]),
-            Value::EndOfMibView => (130, vec![]),
+        let opts = EncodeOptions::default();
+        let mut result = Vec::with_capacity(self.encoded_size(&opts));
+        self.encode_with(bo, &opts, &mut result)?;
+        Ok(result)
+    }
+
+    /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) -> Result<(), Error> {
+        self.encode_with(bo, &EncodeOptions::default(), buf)
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode_with(
+        &self,
+        bo: &ByteOrder,
+        opts: &EncodeOptions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let start = buf.len();
+
+        let ty = match &self.data {
+            Value::Integer(_) => 2,
+            Value::OctetString(_) => 4,
+            Value::Null => 5,
+            Value::ObjectIdentifier(_) => 6,
+            Value::IpAddress(_) => 64,
+            Value::Counter32(_) => 65,
+            Value::Gauge32(_) => 66,
+            Value::TimeTicks(_) => 67,
+            Value::Opaque(_) => 68,
+            Value::Counter64(_) => 70,
+            Value::NoSuchObject => 128,
+            Value::NoSuchInstance => 129,
+            Value::EndOfMibView => 130,
         };
 
-        result.extend(&u16_to_bytes(ty, bo));
-        result.extend(&[0, 0]); /* reserved */
-        result.extend(self.name.to_bytes(bo));
-        result.extend(data);
+        buf.extend(&u16_to_bytes(ty, bo));
+        buf.extend(&[0, 0]); /* reserved */
+        self.name.encode_with(bo, opts, buf);
+
+        match &self.data {
+            Value::Integer(i) => buf.extend(&i32_to_bytes(*i, bo)),
+            Value::OctetString(s) | Value::IpAddress(s) | Value::Opaque(s) => {
+                if let Err(e) = s.encode(bo, buf) {
+                    buf.truncate(start);
+                    return Err(e);
+                }
+            }
+            Value::ObjectIdentifier(i) => i.encode_with(bo, opts, buf),
+            Value::Counter32(c) | Value::Gauge32(c) => buf.extend(&u32_to_bytes(*c, bo)),
+            Value::TimeTicks(t) => buf.extend(&i32_to_bytes(*t, bo)),
+            Value::Counter64(c) => buf.extend(&u64_to_bytes(*c, bo)),
+            Value::Null | Value::NoSuchObject | Value::NoSuchInstance | Value::EndOfMibView => (),
+        }
 
-        Ok(result)
+        Ok(())
     }
 
     fn byte_size(&self) -> usize {
         self.name.byte_size() + self.data.byte_size()
     }
 
+    fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        self.name.encoded_size(opts) + self.data.encoded_size(opts)
+    }
+
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        if b.len() < size_of::<u16>() {
-            return Err(Error::from(ErrorKind::InvalidData));
-        }
         let ty = bytes_to_u16(b, bo)?;
-        let mut b = b.get(4..).ok_or(ErrorKind::InvalidData)?; // reserved;
+        let b = skip(b, size_of::<u32>())?; // type + reserved
 
-        if b.len() < size_of::<u8>() {
-            return Err(Error::from(ErrorKind::InvalidData));
-        }
-        let n_subids = b[0] as usize;
-        let len = 4 /* ID "header" */ + n_subids * size_of::<u32>();
-        let sl = b.get(..len).ok_or(ErrorKind::InvalidData)?;
-        let name = ID::from_bytes(sl, bo)?;
-        b = b.get(len..).ok_or(ErrorKind::InvalidData)?;
+        let name = ID::from_bytes(b, bo).at(size_of::<u32>())?;
+        let offset = size_of::<u32>() + name.byte_size();
+        let b = skip(b, name.byte_size()).at(size_of::<u32>())?;
 
         let data = match ty {
-            2 => Value::Integer(bytes_to_i32(b, bo)?),
+            2 => Value::Integer(bytes_to_i32(b, bo).at(offset)?),
             4 => {
-                let os = OctetString::from_bytes(b, bo)?;
+                let os = OctetString::from_bytes(b, bo).at(offset)?;
                 Value::OctetString(os)
             }
             5 => Value::Null,
-            6 => Value::ObjectIdentifier(ID::from_bytes(b, bo)?),
+            6 => Value::ObjectIdentifier(ID::from_bytes(b, bo).at(offset)?),
             64 => {
-                let os = OctetString::from_bytes(b, bo)?;
+                let os = OctetString::from_bytes(b, bo).at(offset)?;
                 Value::IpAddress(os)
             }
-            65 => Value::Counter32(bytes_to_u32(b, bo)?),
-            66 => Value::Gauge32(bytes_to_u32(b, bo)?),
-            67 => Value::TimeTicks(bytes_to_i32(b, bo)?),
+            65 => Value::Counter32(bytes_to_u32(b, bo).at(offset)?),
+            66 => Value::Gauge32(bytes_to_u32(b, bo).at(offset)?),
+            67 => Value::TimeTicks(bytes_to_i32(b, bo).at(offset)?),
             68 => {
-                let os = OctetString::from_bytes(b, bo)?;
+                let os = OctetString::from_bytes(b, bo).at(offset)?;
                 Value::Opaque(os)
             }
-            70 => Value::Counter64(bytes_to_u64(b, bo)?),
+            70 => Value::Counter64(bytes_to_u64(b, bo).at(offset)?),
             128 => Value::NoSuchObject,
             129 => Value::NoSuchInstance,
             130 => Value::EndOfMibView,
-            _ => return Err(Error::from(ErrorKind::InvalidData)),
+            _ => return Err(Error::UnknownValueType { offset: 0, ty }),
         };
 
         Ok(Self { name, data })
@@ -174,25 +448,52 @@ pub struct VarBindList(pub Vec<VarBind>);
 impl VarBindList {
     /// serialize to bytes
     pub fn to_bytes(&self, bo: &ByteOrder) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
+        let opts = EncodeOptions::default();
+        let mut result = Vec::with_capacity(self.encoded_size(&opts));
+        self.encode_with(bo, &opts, &mut result)?;
+        Ok(result)
+    }
+
+    /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode(&self, bo: &ByteOrder, buf: &mut Vec<u8>) -> Result<(), Error> {
+        self.encode_with(bo, &EncodeOptions::default(), buf)
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode_with(
+        &self,
+        bo: &ByteOrder,
+        opts: &EncodeOptions,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let start = buf.len();
 
         for r in &self.0 {
-            result.extend(r.to_bytes(bo)?);
+            if let Err(e) = r.encode_with(bo, opts, buf) {
+                buf.truncate(start);
+                return Err(e);
+            }
         }
 
-        Ok(result)
+        Ok(())
+    }
+
+    pub(crate) fn encoded_size(&self, opts: &EncodeOptions) -> usize {
+        self.0.iter().map(|vb| vb.encoded_size(opts)).sum()
     }
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        let mut b = b;
+        let mut offset = 0;
         let mut varbinds = Vec::new();
 
-        while !b.is_empty() {
-            let varbind = VarBind::from_bytes(b, bo)?;
-            let size = varbind.byte_size();
+        while offset < b.len() {
+            let rest = &b[offset..];
+            let varbind = VarBind::from_bytes(rest, bo).at(offset)?;
+            // the last element has to be complete, including its padding
+            skip(rest, varbind.byte_size()).at(offset)?;
+            offset += varbind.byte_size();
             varbinds.push(varbind);
-            b = b.get(size..).ok_or(ErrorKind::InvalidData)?;
         }
 
         Ok(Self(varbinds))
@@ -243,7 +544,7 @@ mod tests {
             varbinds.push(VarBind::new(id1, Value::Integer(42)));
             varbinds.push(VarBind::new(
                 id2,
-                Value::OctetString(OctetString("x".to_string())),
+                Value::OctetString(OctetString::from("x")),
             ));
             varbinds.push(VarBind::new(
                 id3,
@@ -292,7 +593,7 @@ mod tests {
     fn varbind_serde_octetstring() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
             let id = ID::from_str("1.2.3").unwrap();
-            let expected = VarBind::new(id, Value::OctetString(OctetString("rck".to_string())));
+            let expected = VarBind::new(id, Value::OctetString(OctetString::from("rck")));
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = VarBind::from_bytes(bytes.as_slice(), &bo).unwrap();
             assert_eq!(expected, got);
@@ -303,7 +604,7 @@ mod tests {
     fn varbind_octetstring_to_bytes_len() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
             let id = ID::from_str("1.2.3").unwrap();
-            let vb = VarBind::new(id, Value::OctetString(OctetString("rck".to_string())));
+            let vb = VarBind::new(id, Value::OctetString(OctetString::from("rck")));
             assert_eq!(vb.to_bytes(&bo).unwrap().len(), vb.byte_size());
         }
     }
@@ -358,7 +659,10 @@ mod tests {
     fn varbind_serde_ipaddress() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
             let id = ID::from_str("1.2.3").unwrap();
-            let expected = VarBind::new(id, Value::IpAddress(OctetString("1234".to_string())));
+            let expected = VarBind::new(
+                id,
+                Value::IpAddress(OctetString::from(vec![192, 168, 0, 1])),
+            );
             let bytes = expected.to_bytes(&bo).unwrap();
             let got = VarBind::from_bytes(bytes.as_slice(), &bo).unwrap();
             assert_eq!(expected, got);
@@ -369,11 +673,32 @@ mod tests {
     fn varbind_ipaddress_to_bytes_len() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
             let id = ID::from_str("1.2.3").unwrap();
-            let vb = VarBind::new(id, Value::IpAddress(OctetString("1234".to_string())));
+            let vb = VarBind::new(
+                id,
+                Value::IpAddress(OctetString::from(vec![192, 168, 0, 1])),
+            );
             assert_eq!(vb.to_bytes(&bo).unwrap().len(), vb.byte_size());
         }
     }
 
+    #[test]
+    fn varbind_serde_opaque() {
+        for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let id = ID::from_str("1.2.3").unwrap();
+            // BER encoded Opaque Float, not valid UTF-8
+            let expected = VarBind::new(
+                id,
+                Value::Opaque(OctetString::from(vec![
+                    0x9f, 0x78, 0x04, 0x3f, 0x80, 0x00, 0x00,
+                ])),
+            );
+            let bytes = expected.to_bytes(&bo).unwrap();
+            let got = VarBind::from_bytes(bytes.as_slice(), &bo).unwrap();
+            assert_eq!(expected, got);
+            assert_eq!(bytes.len(), expected.byte_size());
+        }
+    }
+
     #[test]
     fn varbind_serde_counter32() {
         for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
@@ -513,6 +838,60 @@ mod tests {
             assert_eq!(vb.to_bytes(&bo).unwrap().len(), vb.byte_size());
         }
     }
+    #[test]
+    fn varbind_unknown_type() {
+        let id = ID::from_str("1.2.3").unwrap();
+        let mut bytes = VarBind::new(id, Value::Integer(42))
+            .to_bytes(&ByteOrder::LittleEndian)
+            .unwrap();
+        bytes[0] = 3;
+        assert_eq!(
+            VarBind::from_bytes(&bytes, &ByteOrder::LittleEndian),
+            Err(Error::UnknownValueType { offset: 0, ty: 3 })
+        );
+    }
+
+    #[test]
+    fn varbindlist_truncated_offset() {
+        let bo = ByteOrder::LittleEndian;
+        let id = ID::from_str("1.2.3").unwrap();
+        let vb = VarBind::new(id, Value::Counter64(1));
+        let mut bytes = vb.to_bytes(&bo).unwrap();
+        bytes.extend(vb.to_bytes(&bo).unwrap());
+        bytes.truncate(bytes.len() - 4);
+
+        // second varbind, data after type + reserved + ID
+        assert_eq!(
+            VarBindList::from_bytes(&bytes, &bo),
+            Err(Error::Truncated {
+                offset: vb.byte_size() + 4 + 16,
+                needed: 8,
+                available: 4
+            })
+        );
+    }
+
+    #[test]
+    fn varbindlist_truncated_padding() {
+        let bo = ByteOrder::LittleEndian;
+        let vb = VarBind::new(
+            ID::from_str("1.2").unwrap(),
+            Value::OctetString(OctetString::from("hello")),
+        );
+        let mut bytes = vb.to_bytes(&bo).unwrap();
+        // cut off the 3 padding bytes of "hello"
+        bytes.truncate(bytes.len() - 3);
+
+        assert_eq!(
+            VarBindList::from_bytes(&bytes, &bo),
+            Err(Error::Truncated {
+                offset: 0,
+                needed: vb.byte_size(),
+                available: bytes.len()
+            })
+        );
+    }
+
     #[test]
     fn varbindlist_intoiter() {
         fn get(vbl: &VarBindList) -> Vec<VarBind> {
@@ -533,4 +912,97 @@ mod tests {
 
         assert_eq!(expected, got);
     }
+
+    #[test]
+    fn value_from() {
+        assert_eq!(Value::from(-3), Value::Integer(-3));
+        assert_eq!(Value::from(3u64), Value::Counter64(3));
+        assert_eq!(Value::from(true), Value::Integer(1));
+        assert_eq!(Value::from(false), Value::Integer(2));
+        assert_eq!(
+            Value::from(Ipv4Addr::new(192, 168, 0, 1)),
+            Value::IpAddress(OctetString::from(vec![192, 168, 0, 1]))
+        );
+        assert_eq!(
+            Value::from(&b"ab"[..]),
+            Value::OctetString(OctetString::from("ab"))
+        );
+        assert_eq!(Value::from("ab"), Value::from(b"ab".to_vec()));
+        assert_eq!(Value::from(String::from("ab")), Value::from("ab"));
+        assert_eq!(
+            Value::from(ID::from_str("1.2").unwrap()),
+            Value::ObjectIdentifier(ID::from_str("1.2").unwrap())
+        );
+        assert_eq!(
+            Value::try_from(Duration::from_millis(1234)),
+            Ok(Value::TimeTicks(123))
+        );
+        // TimeTicks are unsigned
+        let days_365 = Duration::from_secs(365 * 24 * 3600);
+        let ticks = Value::try_from(days_365).unwrap();
+        assert_eq!(ticks, Value::TimeTicks(3_153_600_000u32 as i32));
+        assert_eq!(ticks.as_duration(), Ok(days_365));
+        assert_eq!(
+            Value::try_from(Duration::from_millis(u32::MAX as u64 * 10 + 10)),
+            Err(ValueError::Invalid {
+                expected: "TimeTicks"
+            })
+        );
+    }
+
+    #[test]
+    fn value_extract() {
+        assert_eq!(Value::Integer(-3).as_i32(), Ok(-3));
+        assert_eq!(Value::Counter32(3).as_counter32(), Ok(3));
+        assert_eq!(Value::Gauge32(3).as_gauge32(), Ok(3));
+        assert_eq!(Value::Counter64(3).as_u64(), Ok(3));
+        assert_eq!(
+            Value::TimeTicks(123).as_duration(),
+            Ok(Duration::from_millis(1230))
+        );
+        assert_eq!(
+            Value::TimeTicks(-1).as_duration(),
+            Ok(Duration::from_millis(u32::MAX as u64 * 10))
+        );
+        assert_eq!(Value::from("ab").as_bytes(), Ok(&b"ab"[..]));
+        assert_eq!(
+            Value::from(ID::from_str("1.2").unwrap()).as_id(),
+            Ok(&ID::from_str("1.2").unwrap())
+        );
+        let a = Ipv4Addr::new(10, 0, 0, 1);
+        assert_eq!(Value::from(a).as_ipv4(), Ok(a));
+        assert_eq!(Value::from(true).as_bool(), Ok(true));
+        assert_eq!(Value::from(false).as_bool(), Ok(false));
+
+        assert_eq!(
+            Value::Gauge32(3).as_counter32(),
+            Err(ValueError::WrongType {
+                expected: "Counter32",
+                actual: "Gauge32"
+            })
+        );
+        assert_eq!(
+            Value::NoSuchInstance.as_bool(),
+            Err(ValueError::WrongType {
+                expected: "TruthValue",
+                actual: "NoSuchInstance"
+            })
+        );
+        assert_eq!(
+            Value::Integer(0).as_bool(),
+            Err(ValueError::Invalid {
+                expected: "TruthValue"
+            })
+        );
+        assert_eq!(
+            Value::IpAddress(OctetString::from(vec![1, 2, 3])).as_ipv4(),
+            Err(ValueError::Invalid {
+                expected: "IpAddress"
+            })
+        );
+        assert_eq!(
+            Value::Null.as_i32().unwrap_err().to_string(),
+            "expected Integer value, got Null"
+        );
+    }
 }
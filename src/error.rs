@@ -0,0 +1,257 @@
+//! Error type used by all encoders and decoders of this crate
+//!
+//! Decoding errors carry the byte offset where decoding failed. Offsets are relative to the start of the buffer
+//! passed to the `from_bytes()` that returned the error, so for PDUs they are relative to the start of the Header.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Error returned when encoding or decoding fails
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// the buffer ended before a field could be decoded
+    Truncated {
+        /// offset of the field that could not be decoded
+        offset: usize,
+        /// number of bytes required to decode the field
+        needed: usize,
+        /// number of bytes that were available
+        available: usize,
+    },
+    /// the PDU type in the Header is not known
+    UnknownPduType {
+        /// offset of the type byte
+        offset: usize,
+        /// the unknown type
+        ty: u8,
+    },
+    /// the type tag of a VarBind is not known
+    UnknownValueType {
+        /// offset of the type tag
+        offset: usize,
+        /// the unknown type tag
+        ty: u16,
+    },
+    /// the reason of a Close PDU is not known
+    InvalidCloseReason {
+        /// offset of the reason byte
+        offset: usize,
+        /// the unknown reason
+        reason: u8,
+    },
+    /// the error code of a Response PDU is not known
+    InvalidResError {
+        /// offset of the error code
+        offset: usize,
+        /// the unknown error code
+        code: u16,
+    },
+    /// the ID has more sub-identifiers than allowed
+    IdTooLong {
+        /// offset of the ID
+        offset: usize,
+        /// number of sub-identifiers
+        n_subid: usize,
+    },
+    /// a length that has to be a multiple of 4 is not
+    Padding {
+        /// offset of the length field
+        offset: usize,
+        /// the unaligned length
+        len: usize,
+    },
+    /// the Header announces more payload than the buffer contains
+    PayloadLength {
+        /// offset where the payload starts
+        offset: usize,
+        /// payload length according to the Header
+        expected: u32,
+        /// number of payload bytes that were available
+        available: usize,
+    },
+    /// the Header announces more payload than the receiver is willing to accept
+    PayloadTooLarge {
+        /// offset of the payload length field
+        offset: usize,
+        /// payload length according to the Header
+        len: u32,
+        /// maximum accepted payload length
+        max: u32,
+    },
+    /// reserved bits of the Header flags are set
+    ReservedFlags {
+        /// offset of the flags
+        offset: usize,
+        /// the flags
+        flags: u8,
+    },
+    /// a value can not be represented in the size the wire format allows for it
+    ValueOutOfRange {
+        /// name of the field
+        field: &'static str,
+    },
+    /// `range_subid` and `upper_bound` do not describe a valid range registration
+    InvalidRange {
+        /// the (1 based) position of the range sub-identifier
+        range_subid: u8,
+        /// the upper bound of the range
+        upper_bound: Option<u32>,
+    },
+    /// a string could not be parsed as OID
+    InvalidId {
+        /// the string that failed to parse
+        input: String,
+    },
+}
+
+impl Error {
+    /// offset where decoding failed, `None` for errors that are not related to decoding bytes
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Truncated { offset, .. }
+            | Self::UnknownPduType { offset, .. }
+            | Self::UnknownValueType { offset, .. }
+            | Self::InvalidCloseReason { offset, .. }
+            | Self::InvalidResError { offset, .. }
+            | Self::IdTooLong { offset, .. }
+            | Self::Padding { offset, .. }
+            | Self::PayloadLength { offset, .. }
+            | Self::PayloadTooLarge { offset, .. }
+            | Self::ReservedFlags { offset, .. } => Some(*offset),
+            Self::ValueOutOfRange { .. } | Self::InvalidRange { .. } | Self::InvalidId { .. } => {
+                None
+            }
+        }
+    }
+
+    // moves the offset by `by` bytes, used when a nested decoder reports an error relative to its own buffer
+    pub(crate) fn shift(mut self, by: usize) -> Self {
+        match &mut self {
+            Self::Truncated { offset, .. }
+            | Self::UnknownPduType { offset, .. }
+            | Self::UnknownValueType { offset, .. }
+            | Self::InvalidCloseReason { offset, .. }
+            | Self::InvalidResError { offset, .. }
+            | Self::IdTooLong { offset, .. }
+            | Self::Padding { offset, .. }
+            | Self::PayloadLength { offset, .. }
+            | Self::PayloadTooLarge { offset, .. }
+            | Self::ReservedFlags { offset, .. } => *offset += by,
+            Self::ValueOutOfRange { .. } | Self::InvalidRange { .. } | Self::InvalidId { .. } => (),
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated {
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "truncated buffer at offset {}: needed {} bytes, {} available",
+                offset, needed, available
+            ),
+            Self::UnknownPduType { offset, ty } => {
+                write!(f, "unknown PDU type {} at offset {}", ty, offset)
+            }
+            Self::UnknownValueType { offset, ty } => {
+                write!(f, "unknown value type {} at offset {}", ty, offset)
+            }
+            Self::InvalidCloseReason { offset, reason } => {
+                write!(f, "invalid close reason {} at offset {}", reason, offset)
+            }
+            Self::InvalidResError { offset, code } => {
+                write!(f, "invalid response error {} at offset {}", code, offset)
+            }
+            Self::IdTooLong { offset, n_subid } => write!(
+                f,
+                "ID with {} sub-identifiers at offset {} is too long",
+                n_subid, offset
+            ),
+            Self::Padding { offset, len } => write!(
+                f,
+                "length {} at offset {} is not a multiple of 4",
+                len, offset
+            ),
+            Self::PayloadLength {
+                offset,
+                expected,
+                available,
+            } => write!(
+                f,
+                "payload at offset {} announced {} bytes, {} available",
+                offset, expected, available
+            ),
+            Self::PayloadTooLarge { offset, len, max } => write!(
+                f,
+                "payload length {} at offset {} exceeds maximum of {}",
+                len, offset, max
+            ),
+            Self::ReservedFlags { offset, flags } => write!(
+                f,
+                "reserved bits set in flags {:#04x} at offset {}",
+                flags, offset
+            ),
+            Self::ValueOutOfRange { field } => write!(f, "value of {} out of range", field),
+            Self::InvalidRange {
+                range_subid,
+                upper_bound,
+            } => write!(
+                f,
+                "range_subid {} with upper_bound {:?} is not a valid range",
+                range_subid, upper_bound
+            ),
+            Self::InvalidId { input } => write!(f, "'{}' is not a valid ID", input),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+// for backwards compatibility with the times where everything returned io::Error
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+pub(crate) trait ResultExt<T> {
+    // shift the offset of an error, see Error::shift()
+    fn at(self, offset: usize) -> Result<T, Error>;
+}
+
+impl<T> ResultExt<T> for Result<T, Error> {
+    fn at(self, offset: usize) -> Result<T, Error> {
+        self.map_err(|e| e.shift(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_shift() {
+        let e = Error::Truncated {
+            offset: 4,
+            needed: 4,
+            available: 0,
+        };
+        assert_eq!(e.shift(20).offset(), Some(24));
+
+        let e = Error::ValueOutOfRange { field: "timeout" };
+        assert_eq!(e.shift(20).offset(), None);
+    }
+
+    #[test]
+    fn error_into_io() {
+        let e = io::Error::from(Error::UnknownPduType { offset: 1, ty: 42 });
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,337 @@
+//! Splitting a byte stream into complete PDUs
+//!
+//! AgentX runs over stream transports, so a single read might return a partial PDU or several PDUs at once.
+//! [FrameBuffer] collects bytes and hands out complete frames, [FrameReader] does the same on top of any
+//! `std::io::Read`.
+//!
+//! Note that after a decoding error (e.g., an unknown PDU type or a payload that is too large) the stream is out of
+//! sync, the only sane thing to do is to close the session.
+
+use std::io::{self, Read};
+
+use crate::pdu::{Header, Pdu, HEADER_SIZE};
+use crate::Error;
+
+/// default for the maximum accepted payload length
+pub const DEFAULT_MAX_PAYLOAD_LENGTH: u32 = 64 * 1024;
+
+/// A complete PDU as received from the wire
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Frame {
+    header: Header,
+    bytes: Vec<u8>,
+}
+
+impl Frame {
+    /// Header of the PDU
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// bytes of the PDU including the Header
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// consume the Frame and return the bytes of the PDU including the Header
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// decode the PDU
+    pub fn to_pdu(&self) -> Result<Pdu, Error> {
+        Pdu::from_bytes(&self.bytes)
+    }
+}
+
+/// Buffer that gets fed bytes as they arrive and returns complete frames
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::framing::FrameBuffer;
+/// # use agentx::pdu::Ping;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let bytes = Ping::new().to_bytes()?;
+///
+/// let mut buf = FrameBuffer::new();
+/// buf.extend(&bytes[..10]);
+/// assert!(buf.next_frame()?.is_none());
+/// buf.extend(&bytes[10..]);
+/// let frame = buf.next_frame()?.expect("complete frame");
+/// assert_eq!(frame.as_bytes(), bytes.as_slice());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameBuffer {
+    buf: Vec<u8>,
+    max_payload_length: u32,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            max_payload_length: DEFAULT_MAX_PAYLOAD_LENGTH,
+        }
+    }
+}
+
+impl FrameBuffer {
+    /// create a FrameBuffer that accepts payloads up to [DEFAULT_MAX_PAYLOAD_LENGTH]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// create a FrameBuffer that accepts payloads up to `max_payload_length` bytes
+    pub fn with_max_payload_length(max_payload_length: u32) -> Self {
+        Self {
+            max_payload_length,
+            ..Default::default()
+        }
+    }
+
+    /// append received bytes
+    pub fn extend(&mut self, b: &[u8]) {
+        self.buf.extend_from_slice(b);
+    }
+
+    /// number of buffered bytes that are not yet part of a returned frame
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if there are no buffered bytes.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// returns the next complete frame, `None` if more bytes are needed
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+        let header = match self.buf.get(..HEADER_SIZE) {
+            None => return Ok(None),
+            Some(b) => Header::from_bytes(b)?,
+        };
+
+        if header.payload_length > self.max_payload_length {
+            return Err(Error::PayloadTooLarge {
+                offset: 16,
+                len: header.payload_length,
+                max: self.max_payload_length,
+            });
+        }
+
+        let len = HEADER_SIZE + header.payload_length as usize;
+        if self.buf.len() < len {
+            return Ok(None);
+        }
+
+        let rest = self.buf.split_off(len);
+        let bytes = std::mem::replace(&mut self.buf, rest);
+
+        Ok(Some(Frame { header, bytes }))
+    }
+}
+
+/// Reads complete frames from a `std::io::Read`
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::framing::FrameReader;
+/// # use agentx::pdu::{Pdu, Ping};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut bytes = Ping::new().to_bytes()?;
+/// bytes.extend(Ping::new().to_bytes()?);
+///
+/// let mut reader = FrameReader::new(bytes.as_slice());
+/// for _ in 0..2 {
+///     let frame = reader.read_frame()?;
+///     assert!(matches!(frame.to_pdu()?, Pdu::Ping(_)));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FrameReader<R> {
+    inner: R,
+    buf: FrameBuffer,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// create a FrameReader that accepts payloads up to [DEFAULT_MAX_PAYLOAD_LENGTH]
+    pub fn new(inner: R) -> Self {
+        Self::with_max_payload_length(inner, DEFAULT_MAX_PAYLOAD_LENGTH)
+    }
+
+    /// create a FrameReader that accepts payloads up to `max_payload_length` bytes
+    pub fn with_max_payload_length(inner: R, max_payload_length: u32) -> Self {
+        Self {
+            inner,
+            buf: FrameBuffer::with_max_payload_length(max_payload_length),
+        }
+    }
+
+    /// reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// consume the FrameReader and return the underlying reader, buffered bytes are lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// read until a complete frame is available. Decoding errors are returned as `ErrorKind::InvalidData` with the
+    /// [Error] as inner error, a closed stream as `ErrorKind::UnexpectedEof`.
+    pub fn read_frame(&mut self) -> io::Result<Frame> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(frame) = self.buf.next_frame()? {
+                return Ok(frame);
+            }
+
+            let n = match self.inner.read(&mut chunk) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "stream closed with {} bytes of an incomplete frame",
+                        self.buf.len()
+                    ),
+                ));
+            }
+            self.buf.extend(&chunk[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::ID;
+    use crate::pdu::{Close, CloseReason, Open, Ping};
+    use std::str::FromStr;
+
+    // returns at most n bytes per read
+    struct Chunked<'a> {
+        b: &'a [u8],
+        n: usize,
+    }
+
+    impl<'a> Read for Chunked<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.n.min(self.b.len()).min(buf.len());
+            buf[..n].copy_from_slice(&self.b[..n]);
+            self.b = &self.b[n..];
+            Ok(n)
+        }
+    }
+
+    fn stream() -> Vec<u8> {
+        let mut bytes = Open::new(ID::from_str("1.2.3").unwrap(), "rck")
+            .to_bytes()
+            .unwrap();
+        bytes.extend(Ping::new().to_bytes().unwrap());
+        bytes.extend(Close::new(CloseReason::Shutdown).to_bytes().unwrap());
+        bytes
+    }
+
+    #[test]
+    fn framebuffer_partial() {
+        let bytes = stream();
+        let mut buf = FrameBuffer::new();
+        let mut frames = Vec::new();
+
+        for b in bytes.chunks(3) {
+            buf.extend(b);
+            while let Some(frame) = buf.next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert!(buf.is_empty());
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(frames[0].to_pdu().unwrap(), Pdu::Open(_)));
+        assert!(matches!(frames[1].to_pdu().unwrap(), Pdu::Ping(_)));
+        assert!(matches!(frames[2].to_pdu().unwrap(), Pdu::Close(_)));
+    }
+
+    #[test]
+    fn framebuffer_multiple() {
+        let bytes = stream();
+        let mut buf = FrameBuffer::new();
+        buf.extend(&bytes);
+
+        let open = buf.next_frame().unwrap().unwrap();
+        assert_eq!(open.header().payload_length, 28);
+        assert_eq!(open.as_bytes().len(), HEADER_SIZE + 28);
+        assert!(buf.next_frame().unwrap().is_some());
+        assert!(buf.next_frame().unwrap().is_some());
+        assert!(buf.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn framebuffer_too_large() {
+        let bytes = stream();
+        let mut buf = FrameBuffer::with_max_payload_length(16);
+        buf.extend(&bytes);
+
+        assert_eq!(
+            buf.next_frame(),
+            Err(Error::PayloadTooLarge {
+                offset: 16,
+                len: 28,
+                max: 16
+            })
+        );
+    }
+
+    #[test]
+    fn framereader_chunked() {
+        let bytes = stream();
+        for n in vec![1, 7, 20, 4096] {
+            let mut reader = FrameReader::new(Chunked { b: &bytes, n });
+            assert!(matches!(
+                reader.read_frame().unwrap().to_pdu().unwrap(),
+                Pdu::Open(_)
+            ));
+            assert!(matches!(
+                reader.read_frame().unwrap().to_pdu().unwrap(),
+                Pdu::Ping(_)
+            ));
+            assert!(matches!(
+                reader.read_frame().unwrap().to_pdu().unwrap(),
+                Pdu::Close(_)
+            ));
+            assert_eq!(
+                reader.read_frame().unwrap_err().kind(),
+                io::ErrorKind::UnexpectedEof
+            );
+        }
+    }
+
+    #[test]
+    fn framereader_invalid() {
+        let mut bytes = stream();
+        bytes[1] = 42;
+        let mut reader = FrameReader::new(bytes.as_slice());
+        let err = reader.read_frame().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(&Error::UnknownPduType { offset: 1, ty: 42 })
+        );
+    }
+}
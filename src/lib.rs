@@ -5,16 +5,26 @@
 //! This library implements all PDU types and encodings according to [RFC2741](https://datatracker.ietf.org/doc/html/rfc2741).
 //! It provides Rust idiomatic abstractions wherever possible and allows serialization and deserialization to/from wire compatible bytes.
 
+pub mod borrowed;
 pub mod encodings;
+pub mod error;
+pub mod framing;
+pub mod mib;
+pub mod notification;
 pub mod pdu;
+pub mod session;
+pub mod transport;
+
+#[doc(inline)]
+pub use error::Error;
 
 use std::convert::TryInto;
-use std::io::{Error, ErrorKind};
 
 // this looks like a job for the byteorder crate, but unfortunately it does not provide an enum for the different byte order types, LittleEndian and BigEndian are separate enums.
 // so let's have our own enum + some native helpers
 
 /// Byte order used when serializing and deserializing data
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum ByteOrder {
     /// little endian byte order
     LittleEndian,
@@ -23,6 +33,39 @@ pub enum ByteOrder {
     // no NativeEndian as we don't need it in this crate
 }
 
+/// Options used when serializing data
+///
+/// The defaults match what net-snmp sends.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct EncodeOptions {
+    /// send IDs of the form `1.3.6.1.<n>.*` (0 < n < 256) with the `prefix` byte set instead of the first five
+    /// sub-identifiers as described in [Section 5.1](https://datatracker.ietf.org/doc/html/rfc2741#section-5.1),
+    /// saving 20 bytes per ID. Defaults to true.
+    pub prefix_compression: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            prefix_compression: true,
+        }
+    }
+}
+
+// error for a field of `needed` bytes at the start of `b`
+fn truncated(b: &[u8], needed: usize) -> Error {
+    Error::Truncated {
+        offset: 0,
+        needed,
+        available: b.len(),
+    }
+}
+
+// returns the bytes following the first n bytes of b
+fn skip(b: &[u8], n: usize) -> Result<&[u8], Error> {
+    b.get(n..).ok_or_else(|| truncated(b, n))
+}
+
 fn u16_to_bytes(from: u16, bo: &ByteOrder) -> [u8; 2] {
     match bo {
         ByteOrder::BigEndian => from.to_be_bytes(),
@@ -31,8 +74,8 @@ fn u16_to_bytes(from: u16, bo: &ByteOrder) -> [u8; 2] {
 }
 
 fn bytes_to_u16(from: &[u8], bo: &ByteOrder) -> Result<u16, Error> {
-    let from = from.get(0..2).ok_or(ErrorKind::InvalidData)?;
-    let from = from.try_into().map_err(|_| ErrorKind::InvalidData)?;
+    let from = from.get(0..2).ok_or_else(|| truncated(from, 2))?;
+    let from = from.try_into().map_err(|_| truncated(from, 2))?;
     match bo {
         ByteOrder::BigEndian => Ok(u16::from_be_bytes(from)),
         ByteOrder::LittleEndian => Ok(u16::from_le_bytes(from)),
@@ -47,8 +90,8 @@ fn u32_to_bytes(from: u32, bo: &ByteOrder) -> [u8; 4] {
 }
 
 fn bytes_to_u32(from: &[u8], bo: &ByteOrder) -> Result<u32, Error> {
-    let from = from.get(0..4).ok_or(ErrorKind::InvalidData)?;
-    let from = from.try_into().map_err(|_| ErrorKind::InvalidData)?;
+    let from = from.get(0..4).ok_or_else(|| truncated(from, 4))?;
+    let from = from.try_into().map_err(|_| truncated(from, 4))?;
     match bo {
         ByteOrder::BigEndian => Ok(u32::from_be_bytes(from)),
         ByteOrder::LittleEndian => Ok(u32::from_le_bytes(from)),
@@ -63,8 +106,8 @@ fn u64_to_bytes(from: u64, bo: &ByteOrder) -> [u8; 8] {
 }
 
 fn bytes_to_u64(from: &[u8], bo: &ByteOrder) -> Result<u64, Error> {
-    let from = from.get(0..8).ok_or(ErrorKind::InvalidData)?;
-    let from = from.try_into().map_err(|_| ErrorKind::InvalidData)?;
+    let from = from.get(0..8).ok_or_else(|| truncated(from, 8))?;
+    let from = from.try_into().map_err(|_| truncated(from, 8))?;
     match bo {
         ByteOrder::BigEndian => Ok(u64::from_be_bytes(from)),
         ByteOrder::LittleEndian => Ok(u64::from_le_bytes(from)),
@@ -79,8 +122,8 @@ fn i32_to_bytes(from: i32, bo: &ByteOrder) -> [u8; 4] {
 }
 
 fn bytes_to_i32(from: &[u8], bo: &ByteOrder) -> Result<i32, Error> {
-    let from = from.get(0..4).ok_or(ErrorKind::InvalidData)?;
-    let from = from.try_into().map_err(|_| ErrorKind::InvalidData)?;
+    let from = from.get(0..4).ok_or_else(|| truncated(from, 4))?;
+    let from = from.try_into().map_err(|_| truncated(from, 4))?;
     match bo {
         ByteOrder::BigEndian => Ok(i32::from_be_bytes(from)),
         ByteOrder::LittleEndian => Ok(i32::from_le_bytes(from)),
@@ -0,0 +1,24 @@
+//! Serving MIB objects, i.e., answering the "SNMP request processing" PDUs as described in [Section 7.2](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2)
+
+pub mod handler;
+pub mod index;
+pub mod router;
+pub mod scalar;
+pub mod set;
+pub mod table;
+pub mod tree;
+
+#[doc(inline)]
+pub use handler::MibHandler;
+#[doc(inline)]
+pub use index::{IndexError, IndexType};
+#[doc(inline)]
+pub use router::Router;
+#[doc(inline)]
+pub use scalar::Scalar;
+#[doc(inline)]
+pub use set::{SetHandler, SetTransactions};
+#[doc(inline)]
+pub use table::Table;
+#[doc(inline)]
+pub use tree::OidTree;
@@ -0,0 +1,338 @@
+//! MibHandler trait and dispatching of Get, GetNext, and GetBulk as defined in [Section 7.2.3](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.3)
+//!
+//! A [MibHandler] only needs to know how to look up a single object instance and how to find the next one within a
+//! SearchRange. The dispatcher functions take care of the rest: walking the SearchRangeList, checking the results
+//! against the range, filling in `EndOfMibView`, GetBulk repetitions, and building the Response.
+
+use std::cmp::min;
+use std::convert::TryFrom;
+
+use crate::encodings::{SearchRange, SearchRangeList, Value, VarBind, VarBindList, ID};
+use crate::pdu::{Get, GetBulk, GetNext, Header, Pdu, ResError, Response};
+
+/// Access to the MIB objects of a subagent
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{SearchRange, Value, VarBind, ID};
+/// # use agentx::mib::MibHandler;
+/// # use agentx::pdu::ResError;
+/// # use std::str::FromStr;
+/// struct Uptime(u32);
+///
+/// impl MibHandler for Uptime {
+///     fn get(&mut self, id: &ID) -> Result<Value, ResError> {
+///         if *id == ID::from_str("1.3.6.1.4.1.23302.1.0").unwrap() {
+///             Ok(Value::TimeTicks(self.0 as i32))
+///         } else {
+///             Ok(Value::NoSuchObject)
+///         }
+///     }
+///
+///     fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+///         let id = ID::from_str("1.3.6.1.4.1.23302.1.0").unwrap();
+///         if range.contains(&id) {
+///             Ok(Some(VarBind::new(id, Value::TimeTicks(self.0 as i32))))
+///         } else {
+///             Ok(None)
+///         }
+///     }
+/// }
+/// ```
+pub trait MibHandler {
+    /// value of the object instance `id`, as requested by a Get PDU.
+    ///
+    /// Return [Value::NoSuchObject] if there is no such object, or [Value::NoSuchInstance] if the object exists, but
+    /// not the instance. Errors are reported in the Response, `res_index` is set by the dispatcher.
+    fn get(&mut self, id: &ID) -> Result<Value, ResError>;
+
+    /// the lexicographically first object instance within `range`, as requested by a GetNext or GetBulk PDU.
+    ///
+    /// Return `None` if there is none, the dispatcher turns that into [Value::EndOfMibView]. Results outside of
+    /// `range` are treated the same way.
+    fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError>;
+}
+
+// a failed SearchRange: its (0 based) index and the error
+type Failed = (usize, ResError);
+
+// the name used for VarBinds that refer to the start of a range
+fn start_name(range: &SearchRange) -> ID {
+    let mut name = range.start.clone();
+    name.include = 0;
+    name
+}
+
+fn next<H: MibHandler + ?Sized>(handler: &mut H, range: &SearchRange) -> Result<VarBind, ResError> {
+    match handler.get_next(range)? {
+        Some(vb) if range.contains(&vb.name) => Ok(vb),
+        _ => Ok(VarBind::new(start_name(range), Value::EndOfMibView)),
+    }
+}
+
+fn respond(header: &Header, sr: &SearchRangeList, vb: Result<Vec<VarBind>, Failed>) -> Response {
+    let mut response = Response::from_header(header);
+
+    match vb {
+        Ok(vb) => response.vb = Some(VarBindList(vb)),
+        Err((index, e)) => {
+            // like in SNMP the Response to a failed request echoes the requested names
+            response.res_error = e;
+            response.res_index = u16::try_from(index + 1).unwrap_or(u16::MAX);
+            response.vb = Some(VarBindList(
+                sr.0.iter()
+                    .map(|r| VarBind::new(start_name(r), Value::Null))
+                    .collect(),
+            ));
+        }
+    }
+
+    response
+}
+
+/// answer a Get PDU as defined in [Section 7.2.3.1](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.3.1)
+pub fn get<H: MibHandler + ?Sized>(handler: &mut H, pdu: &Get) -> Response {
+    let mut vb = Vec::with_capacity(pdu.sr.len());
+    let mut failed = None;
+
+    for (i, r) in pdu.sr.0.iter().enumerate() {
+        let name = start_name(r);
+        match handler.get(&name) {
+            Ok(value) => vb.push(VarBind::new(name, value)),
+            Err(e) => {
+                failed = Some((i, e));
+                break;
+            }
+        }
+    }
+
+    match failed {
+        None => respond(&pdu.header, &pdu.sr, Ok(vb)),
+        Some(failed) => respond(&pdu.header, &pdu.sr, Err(failed)),
+    }
+}
+
+/// answer a GetNext PDU as defined in [Section 7.2.3.2](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.3.2)
+pub fn get_next<H: MibHandler + ?Sized>(handler: &mut H, pdu: &GetNext) -> Response {
+    let vb = pdu
+        .sr
+        .0
+        .iter()
+        .enumerate()
+        .map(|(i, r)| next(handler, r).map_err(|e| (i, e)))
+        .collect();
+
+    respond(&pdu.header, &pdu.sr, vb)
+}
+
+/// answer a GetBulk PDU as defined in [Section 7.2.3.3](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.3.3)
+///
+/// The first `non_repeaters` SearchRanges are processed like in a GetNext, the remaining ones are repeated up to
+/// `max_repetitions` times, every repetition continuing after the VarBind returned by the previous one. The
+/// repetitions stop early if all repeaters reached the end of their range.
+pub fn get_bulk<H: MibHandler + ?Sized>(handler: &mut H, pdu: &GetBulk) -> Response {
+    let vb = bulk(handler, pdu);
+    respond(&pdu.header, &pdu.sr, vb)
+}
+
+fn bulk<H: MibHandler + ?Sized>(handler: &mut H, pdu: &GetBulk) -> Result<Vec<VarBind>, Failed> {
+    let n = min(pdu.non_repeaters as usize, pdu.sr.len());
+    let (non_repeaters, repeaters) = pdu.sr.0.split_at(n);
+    let mut vb = Vec::new();
+
+    for (i, r) in non_repeaters.iter().enumerate() {
+        vb.push(next(handler, r).map_err(|e| (i, e))?);
+    }
+
+    let mut ranges = repeaters.to_vec();
+    let mut done = vec![false; ranges.len()];
+    for _ in 0..pdu.max_repetitions {
+        if done.iter().all(|d| *d) {
+            break;
+        }
+
+        for (j, r) in ranges.iter_mut().enumerate() {
+            if done[j] {
+                vb.push(VarBind::new(start_name(r), Value::EndOfMibView));
+                continue;
+            }
+
+            let v = next(handler, r).map_err(|e| (n + j, e))?;
+            if v.data == Value::EndOfMibView {
+                done[j] = true;
+            } else {
+                r.start = v.name.clone();
+                r.start.include = 0;
+            }
+            vb.push(v);
+        }
+    }
+
+    Ok(vb)
+}
+
+/// answer a Get, GetNext, or GetBulk PDU. Returns `None` for all other PDUs.
+pub fn dispatch<H: MibHandler + ?Sized>(handler: &mut H, pdu: &Pdu) -> Option<Response> {
+    match pdu {
+        Pdu::Get(p) => Some(get(handler, p)),
+        Pdu::GetNext(p) => Some(get_next(handler, p)),
+        Pdu::GetBulk(p) => Some(get_bulk(handler, p)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::{id, range};
+    use std::collections::BTreeMap;
+
+    struct Map(BTreeMap<ID, Value>);
+
+    impl MibHandler for Map {
+        fn get(&mut self, oid: &ID) -> Result<Value, ResError> {
+            if *oid == id("1.2.99") {
+                return Err(ResError::ProcessingError);
+            }
+            Ok(self.0.get(oid).cloned().unwrap_or(Value::NoSuchObject))
+        }
+
+        // deliberately ignores the range end, the dispatcher has to take care of it
+        fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(k, _)| {
+                    **k > range.start || (range.start.include != 0 && **k == range.start)
+                })
+                .map(|(k, v)| VarBind::new(k.clone(), v.clone())))
+        }
+    }
+
+    fn map() -> Map {
+        let mut m = BTreeMap::new();
+        m.insert(id("1.2.1.0"), Value::Integer(1));
+        m.insert(id("1.2.2.0"), Value::Integer(2));
+        m.insert(id("1.2.3.1"), Value::Integer(31));
+        m.insert(id("1.2.3.2"), Value::Integer(32));
+        Map(m)
+    }
+
+    fn vb(name: &str, data: Value) -> VarBind {
+        VarBind::new(id(name), data)
+    }
+
+    #[test]
+    fn dispatch_get() {
+        let mut pdu = Get::new(SearchRangeList(vec![
+            range("1.2.1.0", 0, ""),
+            range("1.2.4.0", 0, ""),
+        ]));
+        pdu.header.packet_id = 23;
+        let r = get(&mut map(), &pdu);
+
+        assert_eq!(r.header.packet_id, 23);
+        assert_eq!(r.res_error, ResError::NoAgentXError);
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                vb("1.2.1.0", Value::Integer(1)),
+                vb("1.2.4.0", Value::NoSuchObject)
+            ]
+        );
+    }
+
+    #[test]
+    fn dispatch_get_error() {
+        let pdu = Get::new(SearchRangeList(vec![
+            range("1.2.1.0", 0, ""),
+            range("1.2.99", 0, ""),
+        ]));
+        let r = get(&mut map(), &pdu);
+
+        assert_eq!(r.res_error, ResError::ProcessingError);
+        assert_eq!(r.res_index, 2);
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![vb("1.2.1.0", Value::Null), vb("1.2.99", Value::Null)]
+        );
+    }
+
+    #[test]
+    fn dispatch_get_next() {
+        let pdu = GetNext::new(SearchRangeList(vec![
+            range("1.2.1.0", 0, ""),
+            range("1.2.1.0", 1, ""),
+            range("1.2.2.0", 0, "1.2.3.2"),
+            range("1.2.3.1", 0, "1.2.3.2"),
+            range("1.2.3.2", 0, ""),
+        ]));
+        let r = get_next(&mut map(), &pdu);
+
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                vb("1.2.2.0", Value::Integer(2)),
+                vb("1.2.1.0", Value::Integer(1)),
+                vb("1.2.3.1", Value::Integer(31)),
+                vb("1.2.3.1", Value::EndOfMibView),
+                vb("1.2.3.2", Value::EndOfMibView),
+            ]
+        );
+    }
+
+    #[test]
+    fn dispatch_get_bulk() {
+        let mut pdu = GetBulk::new(SearchRangeList(vec![
+            range("1.2.1.0", 0, ""),
+            range("1.2.2", 0, ""),
+            range("1.2.3", 0, "1.2.4"),
+        ]));
+        pdu.non_repeaters = 1;
+        pdu.max_repetitions = 3;
+        let r = get_bulk(&mut map(), &pdu);
+
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                // non repeater
+                vb("1.2.2.0", Value::Integer(2)),
+                // 1st repetition
+                vb("1.2.2.0", Value::Integer(2)),
+                vb("1.2.3.1", Value::Integer(31)),
+                // 2nd repetition
+                vb("1.2.3.1", Value::Integer(31)),
+                vb("1.2.3.2", Value::Integer(32)),
+                // 3rd repetition
+                vb("1.2.3.2", Value::Integer(32)),
+                vb("1.2.3.2", Value::EndOfMibView),
+            ]
+        );
+    }
+
+    #[test]
+    fn dispatch_get_bulk_stops_early() {
+        let mut pdu = GetBulk::new(SearchRangeList(vec![range("1.2.3", 0, "1.2.4")]));
+        pdu.max_repetitions = 10;
+        let r = get_bulk(&mut map(), &pdu);
+
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                vb("1.2.3.1", Value::Integer(31)),
+                vb("1.2.3.2", Value::Integer(32)),
+                vb("1.2.3.2", Value::EndOfMibView),
+            ]
+        );
+    }
+
+    #[test]
+    fn dispatch_other() {
+        let pdu = Pdu::from(Response::new());
+        assert!(dispatch(&mut map(), &pdu).is_none());
+
+        let pdu = Pdu::from(GetNext::new(SearchRangeList(vec![range("1.2", 0, "")])));
+        assert!(dispatch(&mut map(), &pdu).is_some());
+    }
+}
@@ -0,0 +1,479 @@
+//! Table indices, i.e., the mapping between INDEX values and instance OIDs as defined in [RFC 2578 Section 7.7](https://datatracker.ietf.org/doc/html/rfc2578#section-7.7)
+//!
+//! The instance OID of a columnar object is the OID of the column followed by the sub-identifiers of the index values
+//! of the row. Every index object is described by an [IndexType], a table with a compound index by a slice of them.
+//!
+//! # Examples
+//!
+//! ```
+//! # use agentx::encodings::{OctetString, Value, ID};
+//! # use agentx::mib::index::{self, IndexType};
+//! # use std::str::FromStr;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let column = ID::from_str("1.3.6.1.4.1.23302.1.2.1.3")?;
+//! let types = [IndexType::OctetString, IndexType::Integer];
+//! let values = vec![Value::OctetString(OctetString::from("r0")), Value::Integer(7)];
+//!
+//! let instance = index::instance(&column, &types, &values)?;
+//! assert_eq!(instance, ID::from_str("1.3.6.1.4.1.23302.1.2.1.3.2.114.48.7")?);
+//! assert_eq!(index::decode_instance(&column, &types, &instance)?, values);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use crate::encodings::id::MAX_SUB_IDS;
+use crate::encodings::{OctetString, Value, ID};
+
+/// Syntax of an index object, determines how its value is encoded into sub-identifiers
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum IndexType {
+    /// non-negative [Value::Integer], one sub-identifier
+    Integer,
+    /// [Value::Gauge32] (Unsigned32), one sub-identifier. [Value::Counter32] and [Value::TimeTicks] are accepted when
+    /// encoding.
+    Unsigned32,
+    /// variable-length [Value::OctetString], the length followed by one sub-identifier per octet
+    OctetString,
+    /// [Value::OctetString] of fixed length, one sub-identifier per octet
+    FixedOctetString(usize),
+    /// [Value::OctetString] with the IMPLIED keyword, one sub-identifier per octet. Only allowed for the last index.
+    ImpliedOctetString,
+    /// [Value::ObjectIdentifier], the number of sub-identifiers followed by the sub-identifiers
+    ObjectIdentifier,
+    /// [Value::ObjectIdentifier] with the IMPLIED keyword, only the sub-identifiers. Only allowed for the last index.
+    ImpliedObjectIdentifier,
+    /// [Value::IpAddress], four sub-identifiers
+    IpAddress,
+}
+
+impl IndexType {
+    fn implied(&self) -> bool {
+        matches!(
+            self,
+            Self::ImpliedOctetString | Self::ImpliedObjectIdentifier
+        )
+    }
+}
+
+/// Error returned when encoding or decoding table indices
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum IndexError {
+    /// the number of values does not match the number of index types
+    Count {
+        /// number of index types
+        expected: usize,
+        /// number of values
+        got: usize,
+    },
+    /// the value at `position` does not match its index type
+    WrongType {
+        /// position of the index
+        position: usize,
+    },
+    /// the value at `position` can not be encoded, e.g., a negative integer or a string of the wrong length
+    ValueOutOfRange {
+        /// position of the index
+        position: usize,
+    },
+    /// an IMPLIED index at `position` is not the last index
+    ImpliedNotLast {
+        /// position of the index
+        position: usize,
+    },
+    /// the instance OID ends before the index at `position` is complete
+    Truncated {
+        /// position of the index
+        position: usize,
+    },
+    /// the sub-identifiers of the index at `position` do not form a valid value, e.g., an octet greater than 255
+    Malformed {
+        /// position of the index
+        position: usize,
+    },
+    /// there are sub-identifiers left after the last index
+    TrailingSubIds {
+        /// number of sub-identifiers left
+        count: usize,
+    },
+    /// the instance OID is not below the column
+    NotInSubtree,
+    /// the instance OID would exceed [MAX_SUB_IDS]
+    TooLong,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count { expected, got } => {
+                write!(f, "expected {} index values, got {}", expected, got)
+            }
+            Self::WrongType { position } => write!(f, "index {} has the wrong type", position),
+            Self::ValueOutOfRange { position } => {
+                write!(f, "index {} can not be encoded", position)
+            }
+            Self::ImpliedNotLast { position } => {
+                write!(f, "IMPLIED index {} is not the last index", position)
+            }
+            Self::Truncated { position } => write!(f, "index {} is truncated", position),
+            Self::Malformed { position } => write!(f, "index {} is malformed", position),
+            Self::TrailingSubIds { count } => {
+                write!(f, "{} sub-identifiers after the last index", count)
+            }
+            Self::NotInSubtree => write!(f, "instance is not below the column"),
+            Self::TooLong => write!(f, "instance exceeds {} sub-identifiers", MAX_SUB_IDS),
+        }
+    }
+}
+
+impl error::Error for IndexError {}
+
+/// encode index values into sub-identifiers
+pub fn encode(types: &[IndexType], values: &[Value]) -> Result<Vec<u32>, IndexError> {
+    if types.len() != values.len() {
+        return Err(IndexError::Count {
+            expected: types.len(),
+            got: values.len(),
+        });
+    }
+
+    let mut sub_ids = Vec::new();
+    for (position, (ty, value)) in types.iter().zip(values).enumerate() {
+        if ty.implied() && position + 1 != types.len() {
+            return Err(IndexError::ImpliedNotLast { position });
+        }
+        encode_value(*ty, value, &mut sub_ids).map_err(|e| e(position))?;
+        if sub_ids.len() > MAX_SUB_IDS {
+            return Err(IndexError::TooLong);
+        }
+    }
+
+    Ok(sub_ids)
+}
+
+// the error constructor is applied to the position by the caller
+type PositionError = fn(usize) -> IndexError;
+
+fn wrong_type(position: usize) -> IndexError {
+    IndexError::WrongType { position }
+}
+
+fn out_of_range(position: usize) -> IndexError {
+    IndexError::ValueOutOfRange { position }
+}
+
+fn encode_value(ty: IndexType, value: &Value, sub_ids: &mut Vec<u32>) -> Result<(), PositionError> {
+    match (ty, value) {
+        (IndexType::Integer, Value::Integer(i)) => {
+            sub_ids.push(u32::try_from(*i).map_err(|_| out_of_range as PositionError)?)
+        }
+        (IndexType::Unsigned32, Value::Gauge32(u))
+        | (IndexType::Unsigned32, Value::Counter32(u)) => sub_ids.push(*u),
+        (IndexType::Unsigned32, Value::TimeTicks(t)) => sub_ids.push(*t as u32),
+        (IndexType::OctetString, Value::OctetString(s)) => {
+            sub_ids.push(u32::try_from(s.len()).map_err(|_| out_of_range as PositionError)?);
+            sub_ids.extend(s.as_bytes().iter().map(|b| *b as u32));
+        }
+        (IndexType::FixedOctetString(n), Value::OctetString(s)) => {
+            if s.len() != n {
+                return Err(out_of_range);
+            }
+            sub_ids.extend(s.as_bytes().iter().map(|b| *b as u32));
+        }
+        (IndexType::ImpliedOctetString, Value::OctetString(s)) => {
+            sub_ids.extend(s.as_bytes().iter().map(|b| *b as u32))
+        }
+        (IndexType::ObjectIdentifier, Value::ObjectIdentifier(id)) => {
+            sub_ids.push(id.len() as u32);
+            sub_ids.extend(id);
+        }
+        (IndexType::ImpliedObjectIdentifier, Value::ObjectIdentifier(id)) => sub_ids.extend(id),
+        (IndexType::IpAddress, Value::IpAddress(a)) => {
+            if a.len() != 4 {
+                return Err(out_of_range);
+            }
+            sub_ids.extend(a.as_bytes().iter().map(|b| *b as u32));
+        }
+        _ => return Err(wrong_type),
+    }
+
+    Ok(())
+}
+
+/// decode index values from sub-identifiers, all sub-identifiers have to belong to the index
+pub fn decode(types: &[IndexType], sub_ids: &[u32]) -> Result<Vec<Value>, IndexError> {
+    let mut values = Vec::with_capacity(types.len());
+    let mut rest = sub_ids;
+
+    for (position, ty) in types.iter().enumerate() {
+        if ty.implied() && position + 1 != types.len() {
+            return Err(IndexError::ImpliedNotLast { position });
+        }
+        let (value, tail) = decode_value(*ty, rest).map_err(|e| e(position))?;
+        values.push(value);
+        rest = tail;
+    }
+
+    if !rest.is_empty() {
+        return Err(IndexError::TrailingSubIds { count: rest.len() });
+    }
+
+    Ok(values)
+}
+
+fn truncated(position: usize) -> IndexError {
+    IndexError::Truncated { position }
+}
+
+fn malformed(position: usize) -> IndexError {
+    IndexError::Malformed { position }
+}
+
+// splits off the first n sub-identifiers
+fn take(sub_ids: &[u32], n: usize) -> Result<(&[u32], &[u32]), PositionError> {
+    if sub_ids.len() < n {
+        return Err(truncated);
+    }
+    Ok(sub_ids.split_at(n))
+}
+
+// splits off a length followed by that many sub-identifiers
+fn take_counted(sub_ids: &[u32]) -> Result<(&[u32], &[u32]), PositionError> {
+    let (len, rest) = sub_ids.split_first().ok_or(truncated as PositionError)?;
+    take(rest, *len as usize)
+}
+
+fn octets(sub_ids: &[u32]) -> Result<OctetString, PositionError> {
+    let octets = sub_ids
+        .iter()
+        .map(|s| u8::try_from(*s))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| malformed as PositionError)?;
+    Ok(OctetString::from(octets))
+}
+
+fn id(sub_ids: &[u32]) -> Result<ID, PositionError> {
+    ID::try_from(sub_ids.to_vec()).map_err(|_| malformed as PositionError)
+}
+
+fn decode_value(ty: IndexType, sub_ids: &[u32]) -> Result<(Value, &[u32]), PositionError> {
+    let (value, rest) = match ty {
+        IndexType::Integer => {
+            let (i, rest) = take(sub_ids, 1)?;
+            let i = i32::try_from(i[0]).map_err(|_| malformed as PositionError)?;
+            (Value::Integer(i), rest)
+        }
+        IndexType::Unsigned32 => {
+            let (u, rest) = take(sub_ids, 1)?;
+            (Value::Gauge32(u[0]), rest)
+        }
+        IndexType::OctetString => {
+            let (s, rest) = take_counted(sub_ids)?;
+            (Value::OctetString(octets(s)?), rest)
+        }
+        IndexType::FixedOctetString(n) => {
+            let (s, rest) = take(sub_ids, n)?;
+            (Value::OctetString(octets(s)?), rest)
+        }
+        IndexType::ImpliedOctetString => (Value::OctetString(octets(sub_ids)?), &[][..]),
+        IndexType::ObjectIdentifier => {
+            let (i, rest) = take_counted(sub_ids)?;
+            (Value::ObjectIdentifier(id(i)?), rest)
+        }
+        IndexType::ImpliedObjectIdentifier => (Value::ObjectIdentifier(id(sub_ids)?), &[][..]),
+        IndexType::IpAddress => {
+            let (a, rest) = take(sub_ids, 4)?;
+            (Value::IpAddress(octets(a)?), rest)
+        }
+    };
+
+    Ok((value, rest))
+}
+
+/// the instance OID of a columnar object, i.e., `column` followed by the encoded index values
+pub fn instance(column: &ID, types: &[IndexType], values: &[Value]) -> Result<ID, IndexError> {
+    let mut instance = column.clone();
+    instance.include = 0;
+    instance
+        .append(&encode(types, values)?)
+        .map_err(|_| IndexError::TooLong)?;
+    Ok(instance)
+}
+
+/// decode the index values of the instance OID of a columnar object
+pub fn decode_instance(
+    column: &ID,
+    types: &[IndexType],
+    instance: &ID,
+) -> Result<Vec<Value>, IndexError> {
+    let sub_ids = instance
+        .strip_prefix(column)
+        .ok_or(IndexError::NotInSubtree)?;
+    decode(types, sub_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::id;
+
+    fn os(s: &str) -> Value {
+        Value::OctetString(OctetString::from(s))
+    }
+
+    #[test]
+    fn index_round_trip() {
+        let cases = vec![
+            (IndexType::Integer, Value::Integer(42), vec![42]),
+            (
+                IndexType::Unsigned32,
+                Value::Gauge32(u32::MAX),
+                vec![u32::MAX],
+            ),
+            (IndexType::OctetString, os("ab"), vec![2, 97, 98]),
+            (IndexType::OctetString, os(""), vec![0]),
+            (IndexType::FixedOctetString(3), os("abc"), vec![97, 98, 99]),
+            (IndexType::ImpliedOctetString, os("ab"), vec![97, 98]),
+            (
+                IndexType::ObjectIdentifier,
+                Value::ObjectIdentifier(id("1.3.6")),
+                vec![3, 1, 3, 6],
+            ),
+            (
+                IndexType::ImpliedObjectIdentifier,
+                Value::ObjectIdentifier(id("1.3.6")),
+                vec![1, 3, 6],
+            ),
+            (
+                IndexType::IpAddress,
+                Value::IpAddress(OctetString::from(vec![192, 168, 0, 1])),
+                vec![192, 168, 0, 1],
+            ),
+        ];
+
+        for (ty, value, sub_ids) in cases {
+            let values = vec![value];
+            assert_eq!(encode(&[ty], &values), Ok(sub_ids.clone()), "{:?}", ty);
+            assert_eq!(decode(&[ty], &sub_ids), Ok(values), "{:?}", ty);
+        }
+    }
+
+    #[test]
+    fn index_compound() {
+        let column = id("1.3.6.1.2.1.4.20.1.2");
+        let types = [
+            IndexType::IpAddress,
+            IndexType::OctetString,
+            IndexType::Integer,
+            IndexType::ImpliedObjectIdentifier,
+        ];
+        let values = vec![
+            Value::IpAddress(OctetString::from(vec![10, 0, 0, 1])),
+            os("x"),
+            Value::Integer(0),
+            Value::ObjectIdentifier(id("2.5")),
+        ];
+
+        let instance = instance(&column, &types, &values).unwrap();
+        assert_eq!(instance, id("1.3.6.1.2.1.4.20.1.2.10.0.0.1.1.120.0.2.5"));
+        assert_eq!(decode_instance(&column, &types, &instance), Ok(values));
+
+        // counters and timeticks are accepted as Unsigned32, but decoded as Gauge32
+        assert_eq!(
+            encode(&[IndexType::Unsigned32], &[Value::Counter32(5)]),
+            Ok(vec![5])
+        );
+        assert_eq!(
+            encode(
+                &[IndexType::Unsigned32],
+                &[Value::TimeTicks(3_153_600_000u32 as i32)]
+            ),
+            Ok(vec![3_153_600_000])
+        );
+        assert_eq!(
+            decode(&[IndexType::Unsigned32], &[5]),
+            Ok(vec![Value::Gauge32(5)])
+        );
+    }
+
+    #[test]
+    fn index_encode_errors() {
+        assert_eq!(
+            encode(&[IndexType::Integer], &[]),
+            Err(IndexError::Count {
+                expected: 1,
+                got: 0
+            })
+        );
+        assert_eq!(
+            encode(
+                &[IndexType::Integer, IndexType::Integer],
+                &[Value::Integer(1), os("a")]
+            ),
+            Err(IndexError::WrongType { position: 1 })
+        );
+        assert_eq!(
+            encode(&[IndexType::Integer], &[Value::Integer(-1)]),
+            Err(IndexError::ValueOutOfRange { position: 0 })
+        );
+        assert_eq!(
+            encode(&[IndexType::FixedOctetString(2)], &[os("abc")]),
+            Err(IndexError::ValueOutOfRange { position: 0 })
+        );
+        assert_eq!(
+            encode(
+                &[IndexType::ImpliedOctetString, IndexType::Integer],
+                &[os("a"), Value::Integer(1)]
+            ),
+            Err(IndexError::ImpliedNotLast { position: 0 })
+        );
+        assert_eq!(
+            encode(&[IndexType::OctetString], &[os(&"a".repeat(MAX_SUB_IDS))]),
+            Err(IndexError::TooLong)
+        );
+        assert_eq!(
+            instance(
+                &ID::try_from(vec![1; MAX_SUB_IDS]).unwrap(),
+                &[IndexType::Integer],
+                &[Value::Integer(1)]
+            ),
+            Err(IndexError::TooLong)
+        );
+    }
+
+    #[test]
+    fn index_decode_errors() {
+        assert_eq!(
+            decode(&[IndexType::Integer, IndexType::Integer], &[1]),
+            Err(IndexError::Truncated { position: 1 })
+        );
+        assert_eq!(
+            decode(&[IndexType::OctetString], &[3, 97, 98]),
+            Err(IndexError::Truncated { position: 0 })
+        );
+        assert_eq!(
+            decode(&[IndexType::OctetString], &[]),
+            Err(IndexError::Truncated { position: 0 })
+        );
+        assert_eq!(
+            decode(&[IndexType::ImpliedOctetString], &[97, 256]),
+            Err(IndexError::Malformed { position: 0 })
+        );
+        assert_eq!(
+            decode(&[IndexType::Integer], &[u32::MAX]),
+            Err(IndexError::Malformed { position: 0 })
+        );
+        assert_eq!(
+            decode(&[IndexType::Integer], &[1, 2]),
+            Err(IndexError::TrailingSubIds { count: 1 })
+        );
+        assert_eq!(
+            decode_instance(&id("1.2.3"), &[IndexType::Integer], &id("1.2.4.1")),
+            Err(IndexError::NotInSubtree)
+        );
+    }
+}
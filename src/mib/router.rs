@@ -0,0 +1,299 @@
+//! Routing requests to the handlers of registered regions
+//!
+//! A [Router] is a [MibHandler] that owns one handler per region, i.e., per [OidRange] the subagent registers. Get is
+//! answered by the handler whose region contains the requested instance, GetNext and GetBulk by the handler that has
+//! the lexicographically first instance within both the SearchRange and its region.
+
+use std::fmt;
+
+use crate::encodings::{OidRange, SearchRange, Value, VarBind, ID};
+use crate::mib::MibHandler;
+use crate::pdu::{Register, ResError};
+
+type Handler = dyn MibHandler + Send;
+
+/// Handlers keyed by the region they serve
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{OidRange, SearchRange, Value, ID};
+/// # use agentx::mib::{OidTree, Router, Scalar};
+/// # use agentx::mib::MibHandler;
+/// # use std::str::FromStr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut columns = OidTree::new();
+/// columns.insert(ID::from_str("1.3.6.1.4.1.23302.2.1.3.1")?, Value::Integer(31));
+///
+/// let mut router = Router::new();
+/// router.add(
+///     OidRange::subtree(ID::from_str("1.3.6.1.4.1.23302.1.1")?),
+///     Scalar::new(ID::from_str("1.3.6.1.4.1.23302.1.1")?, || Value::Integer(1))?,
+/// );
+/// router.add(OidRange::new(ID::from_str("1.3.6.1.4.1.23302.2.1.2")?, 10, 4)?, columns);
+/// for register in router.registrations() {
+///     // session.register(register)?;
+/// }
+///
+/// let range = SearchRange::new(ID::from_str("1.3.6.1.4.1.23302.1.1.0")?, ID::default());
+/// let vb = router.get_next(&range).ok().flatten().expect("successor");
+/// assert_eq!(vb.name, ID::from_str("1.3.6.1.4.1.23302.2.1.3.1")?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<(OidRange, Box<Handler>)>,
+}
+
+impl Router {
+    /// create a Router without any routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// let `handler` serve the region `range`. If regions overlap, Get requests are routed to the handler that was
+    /// added first.
+    pub fn add<H: MibHandler + Send + 'static>(&mut self, range: OidRange, handler: H) {
+        self.routes.push((range, Box::new(handler)));
+    }
+
+    /// the regions of all routes, in the order they were added
+    pub fn ranges(&self) -> impl Iterator<Item = &OidRange> + '_ {
+        self.routes.iter().map(|(range, _)| range)
+    }
+
+    /// Register PDUs for all regions, see [crate::session::Session::register]
+    pub fn registrations(&self) -> impl Iterator<Item = Register> + '_ {
+        self.ranges()
+            .map(|range| Register::from_range(range.clone()))
+    }
+
+    /// number of routes
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Returns true if there are no routes.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("ranges", &self.ranges().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+// the first ID after the subtree of `id`, the null ID (no end) if there is none
+fn subtree_end(id: &ID) -> ID {
+    let mut id = id.clone();
+    loop {
+        if let Some(sibling) = id.next_sibling() {
+            return sibling;
+        }
+        match id.parent() {
+            Some(parent) => id = parent,
+            None => return ID::default(),
+        }
+    }
+}
+
+// the region as spans of consecutive IDs (start included, end excluded or null), in lexicographical order, starting
+// at the span `from` is in or before. If the range sub-identifier is the last one, the subtrees are adjacent and form a
+// single span.
+fn spans<'a>(range: &'a OidRange, from: &ID) -> Box<dyn Iterator<Item = (ID, ID)> + 'a> {
+    if !range.is_range() || range.range_subid() as usize == range.base().len() {
+        Box::new(std::iter::once((range.first(), subtree_end(&range.last()))))
+    } else {
+        Box::new(range.subtrees_from(from).map(|s| {
+            let end = subtree_end(&s);
+            (s, end)
+        }))
+    }
+}
+
+// the first instance within both `sr` and `range`
+fn next_in(
+    range: &OidRange,
+    handler: &mut Handler,
+    sr: &SearchRange,
+) -> Result<Option<VarBind>, ResError> {
+    for (mut start, end) in spans(range, &sr.start) {
+        // the span is before the SearchRange
+        if !end.is_null() && end <= sr.start {
+            continue;
+        }
+        // this and all following spans are after the SearchRange
+        if !sr.end.is_null() && start >= sr.end {
+            break;
+        }
+
+        if sr.start < start {
+            start.include = 1;
+        } else {
+            start = sr.start.clone();
+        }
+        let end = if end.is_null() || (!sr.end.is_null() && sr.end < end) {
+            sr.end.clone()
+        } else {
+            end
+        };
+
+        let r = SearchRange::new(start, end);
+        match handler.get_next(&r)? {
+            Some(vb) if r.contains(&vb.name) => return Ok(Some(vb)),
+            _ => (),
+        }
+    }
+
+    Ok(None)
+}
+
+impl MibHandler for Router {
+    fn get(&mut self, id: &ID) -> Result<Value, ResError> {
+        match self.routes.iter_mut().find(|(range, _)| range.contains(id)) {
+            Some((_, handler)) => handler.get(id),
+            None => Ok(Value::NoSuchObject),
+        }
+    }
+
+    fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+        let mut first: Option<VarBind> = None;
+        for (region, handler) in self.routes.iter_mut() {
+            if let Some(vb) = next_in(region, handler.as_mut(), range)? {
+                if first.as_ref().map_or(true, |f| vb.name < f.name) {
+                    first = Some(vb);
+                }
+            }
+        }
+        Ok(first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::{id, range};
+    use crate::encodings::SearchRangeList;
+    use crate::mib::{handler, OidTree};
+    use crate::pdu::GetBulk;
+
+    fn tree(ids: &[&str]) -> OidTree {
+        ids.iter()
+            .map(|s| (id(s), Value::OctetString(s.to_string().into())))
+            .collect()
+    }
+
+    // a tree serving more than its regions, the Router must not leak the rest
+    fn router() -> Router {
+        let all = [
+            "1.2.1.0",
+            "1.2.2.1.1",
+            "1.2.2.1.2",
+            "1.2.2.2.1",
+            "1.2.2.2.2",
+            "1.2.2.3.1",
+            "1.2.3.0",
+        ];
+        let mut router = Router::new();
+        // row 2 of columns 1-3
+        router.add(OidRange::new(id("1.2.2.1.2"), 4, 3).unwrap(), tree(&all));
+        router.add(OidRange::subtree(id("1.2.1")), tree(&all));
+        router
+    }
+
+    #[test]
+    fn router_get() {
+        let mut router = router();
+        assert_eq!(router.len(), 2);
+
+        assert_eq!(
+            router.get(&id("1.2.2.2.2")),
+            Ok(Value::OctetString("1.2.2.2.2".to_string().into()))
+        );
+        assert_eq!(
+            router.get(&id("1.2.1.0")),
+            Ok(Value::OctetString("1.2.1.0".to_string().into()))
+        );
+        assert_eq!(router.get(&id("1.2.2.2.1")), Ok(Value::NoSuchObject));
+        assert_eq!(router.get(&id("1.2.3.0")), Ok(Value::NoSuchObject));
+    }
+
+    #[test]
+    fn router_get_next() {
+        let mut router = router();
+        let mut next = |start, include, end| {
+            router
+                .get_next(&range(start, include, end))
+                .unwrap()
+                .map(|vb| vb.name)
+        };
+
+        assert_eq!(next("1", 0, ""), Some(id("1.2.1.0")));
+        assert_eq!(next("1.2.1.0", 0, ""), Some(id("1.2.2.1.2")));
+        assert_eq!(next("1.2.2.1.2", 1, ""), Some(id("1.2.2.1.2")));
+        // 1.2.2.2.1 is outside of the registered rows
+        assert_eq!(next("1.2.2.1.2", 0, ""), Some(id("1.2.2.2.2")));
+        // there is no instance in row 2 of column 3
+        assert_eq!(next("1.2.2.2.2", 0, ""), None);
+        assert_eq!(next("1.2.1.0", 0, "1.2.2.1.2"), None);
+
+        let mut pdu = GetBulk::new(SearchRangeList(vec![range("1.2", 0, "1.3")]));
+        pdu.max_repetitions = 5;
+        let names: Vec<ID> = handler::get_bulk(&mut router, &pdu)
+            .vb
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|vb| vb.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                id("1.2.1.0"),
+                id("1.2.2.1.2"),
+                id("1.2.2.2.2"),
+                id("1.2.2.2.2")
+            ]
+        );
+    }
+
+    #[test]
+    fn router_get_next_large_range() {
+        // column 1 of rows 0 to u32::MAX, only the rows around sr.start may be visited
+        let mut router = Router::new();
+        router.add(
+            OidRange::new(id("1.2.0.1"), 3, u32::MAX).unwrap(),
+            tree(&["1.2.4000000000.1.5", "1.2.4000000001.2.5"]),
+        );
+        assert_eq!(
+            router
+                .get_next(&range("1.2.4000000000", 0, ""))
+                .unwrap()
+                .map(|vb| vb.name),
+            Some(id("1.2.4000000000.1.5"))
+        );
+        assert_eq!(
+            router
+                .get_next(&range("1.2.4000000000.1.5", 0, "1.2.4000000002"))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn router_registrations() {
+        let router = router();
+        let registrations: Vec<Register> = router.registrations().collect();
+        assert_eq!(registrations[0].subtree, id("1.2.2.1.2"));
+        assert_eq!(registrations[0].range_subid, 4);
+        assert_eq!(registrations[0].upper_bound, Some(3));
+        assert_eq!(registrations[1].subtree, id("1.2.1"));
+        assert_eq!(registrations[1].range_subid, 0);
+        assert_eq!(registrations[1].upper_bound, None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Scalar objects, i.e., objects with the single instance `x.0` as defined in [RFC 2578 Section 7.7](https://datatracker.ietf.org/doc/html/rfc2578#section-7.7)
+//!
+//! A [Scalar] answers Get on `x.0` and GetNext on everything before it. Other instances below `x` are
+//! [Value::NoSuchInstance]. With a setter it also implements [SetHandler], so it can be used with
+//! [crate::mib::SetTransactions].
+
+use std::fmt;
+use std::mem;
+
+use crate::encodings::{SearchRange, Value, VarBind, ID};
+use crate::mib::{MibHandler, SetHandler};
+use crate::pdu::{Register, ResError, Unregister};
+use crate::Error;
+
+type Getter = dyn FnMut() -> Value + Send;
+type Setter = dyn FnMut(&Value) -> Result<(), ResError> + Send;
+
+/// Scalar object with a getter and an optional setter
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{OctetString, SearchRange, Value, ID};
+/// # use agentx::mib::{MibHandler, Scalar};
+/// # use std::str::FromStr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut version = Scalar::new(ID::from_str("1.3.6.1.4.1.23302.1.1")?, || {
+///     Value::OctetString(OctetString::from("9.2.1"))
+/// })?
+/// .instance_registration(true);
+/// let register = version.register();
+/// assert_eq!(register.subtree, ID::from_str("1.3.6.1.4.1.23302.1.1.0")?);
+/// assert!(register.header.flags.instance_registration());
+///
+/// let range = SearchRange::new(ID::from_str("1.3.6.1.4.1.23302.1.1")?, ID::default());
+/// let vb = version.get_next(&range).ok().flatten().expect("successor");
+/// assert_eq!(vb.name, ID::from_str("1.3.6.1.4.1.23302.1.1.0")?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scalar {
+    id: ID,
+    instance: ID,
+    instance_registration: bool,
+    get: Box<Getter>,
+    set: Option<Box<Setter>>,
+}
+
+impl Scalar {
+    /// create a read-only scalar for the object `id`, `get` returns the value of `id.0`. Fails if `id.0` is too long.
+    pub fn new<F>(mut id: ID, get: F) -> Result<Self, Error>
+    where
+        F: FnMut() -> Value + Send + 'static,
+    {
+        id.include = 0;
+        let instance = id.child(0)?;
+        Ok(Self {
+            id,
+            instance,
+            instance_registration: false,
+            get: Box::new(get),
+            set: None,
+        })
+    }
+
+    /// make the scalar writable, `set` is called with the new value on CommitSet and with the previous value on
+    /// UndoSet
+    pub fn setter<F>(mut self, set: F) -> Self
+    where
+        F: FnMut(&Value) -> Result<(), ResError> + Send + 'static,
+    {
+        self.set = Some(Box::new(set));
+        self
+    }
+
+    /// register the instance `id.0` with INSTANCE_REGISTRATION instead of the subtree `id`
+    pub fn instance_registration(mut self, instance_registration: bool) -> Self {
+        self.instance_registration = instance_registration;
+        self
+    }
+
+    /// OID of the object
+    pub fn id(&self) -> &ID {
+        &self.id
+    }
+
+    /// OID of the only instance, `id.0`
+    pub fn instance(&self) -> &ID {
+        &self.instance
+    }
+
+    /// Returns true if the scalar has a setter.
+    pub fn writable(&self) -> bool {
+        self.set.is_some()
+    }
+
+    fn region(&self) -> ID {
+        if self.instance_registration {
+            self.instance.clone()
+        } else {
+            self.id.clone()
+        }
+    }
+
+    /// the Register PDU for the scalar, see [crate::session::Session::register]
+    pub fn register(&self) -> Register {
+        let mut register = Register::new(self.region());
+        register
+            .header
+            .flags
+            .set_instance_registration(self.instance_registration);
+        register
+    }
+
+    /// the Unregister PDU for the scalar with the `priority` it was registered with, see
+    /// [crate::session::Session::unregister]
+    pub fn unregister(&self, priority: u8) -> Unregister {
+        Unregister::new(self.region(), priority)
+    }
+
+    /// current value of the instance
+    pub fn value(&mut self) -> Value {
+        (self.get)()
+    }
+}
+
+impl fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scalar")
+            .field("id", &self.id)
+            .field("instance_registration", &self.instance_registration)
+            .field("writable", &self.writable())
+            .finish()
+    }
+}
+
+impl MibHandler for Scalar {
+    fn get(&mut self, id: &ID) -> Result<Value, ResError> {
+        if *id == self.instance {
+            Ok(self.value())
+        } else if id.starts_with(&self.id) {
+            Ok(Value::NoSuchInstance)
+        } else {
+            Ok(Value::NoSuchObject)
+        }
+    }
+
+    fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+        if range.contains(&self.instance) {
+            Ok(Some(VarBind::new(self.instance.clone(), self.value())))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl SetHandler for Scalar {
+    /// the previous value
+    type Undo = Value;
+
+    fn test(&mut self, vb: &VarBind) -> Result<(), ResError> {
+        if vb.name != self.instance {
+            if vb.name.starts_with(&self.id) {
+                return Err(ResError::NoCreation);
+            }
+            return Err(ResError::NotWritable);
+        }
+        if self.set.is_none() {
+            return Err(ResError::NotWritable);
+        }
+        if mem::discriminant(&vb.data) != mem::discriminant(&self.value()) {
+            return Err(ResError::WrongType);
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self, vb: &VarBind) -> Result<Value, ResError> {
+        let previous = self.value();
+        let set = self.set.as_mut().ok_or(ResError::NotWritable)?;
+        set(&vb.data)?;
+        Ok(previous)
+    }
+
+    fn undo(&mut self, _vb: &VarBind, undo: Value) -> Result<(), ResError> {
+        let set = self.set.as_mut().ok_or(ResError::NotWritable)?;
+        set(&undo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::{id, range};
+    use crate::encodings::{SearchRangeList, VarBindList};
+    use crate::mib::handler;
+    use crate::mib::SetTransactions;
+    use crate::pdu::{CleanupSet, CommitSet, GetNext, Pdu, TestSet, UndoSet};
+    use std::convert::TryFrom;
+    use std::sync::{Arc, Mutex};
+
+    fn writable() -> (Scalar, Arc<Mutex<i32>>) {
+        let value = Arc::new(Mutex::new(1));
+        let (get, set) = (value.clone(), value.clone());
+        let scalar = Scalar::new(id("1.2.3"), move || Value::Integer(*get.lock().unwrap()))
+            .unwrap()
+            .setter(move |v| match v {
+                Value::Integer(i) if *i >= 0 => {
+                    *set.lock().unwrap() = *i;
+                    Ok(())
+                }
+                _ => Err(ResError::WrongValue),
+            });
+        (scalar, value)
+    }
+
+    #[test]
+    fn scalar_register() {
+        let scalar = Scalar::new(id("1.2.3"), || Value::Null).unwrap();
+        let register = scalar.register();
+        assert_eq!(register.subtree, id("1.2.3"));
+        assert!(!register.header.flags.instance_registration());
+        assert_eq!(scalar.unregister(127).subtree, id("1.2.3"));
+
+        let scalar = scalar.instance_registration(true);
+        let register = scalar.register();
+        assert_eq!(register.subtree, id("1.2.3.0"));
+        assert!(register.header.flags.instance_registration());
+        assert_eq!(scalar.unregister(127).subtree, id("1.2.3.0"));
+
+        assert!(Scalar::new(ID::try_from(vec![1; 128]).unwrap(), || Value::Null).is_err());
+    }
+
+    #[test]
+    fn scalar_get() {
+        let (mut scalar, _) = writable();
+
+        assert_eq!(scalar.get(&id("1.2.3.0")), Ok(Value::Integer(1)));
+        assert_eq!(scalar.get(&id("1.2.3")), Ok(Value::NoSuchInstance));
+        assert_eq!(scalar.get(&id("1.2.3.1")), Ok(Value::NoSuchInstance));
+        assert_eq!(scalar.get(&id("1.2.3.0.0")), Ok(Value::NoSuchInstance));
+        assert_eq!(scalar.get(&id("1.2.4.0")), Ok(Value::NoSuchObject));
+
+        let next = |scalar: &mut Scalar, start, include, end| {
+            scalar
+                .get_next(&range(start, include, end))
+                .unwrap()
+                .map(|vb| vb.name)
+        };
+        assert_eq!(next(&mut scalar, "1.2.3", 0, ""), Some(id("1.2.3.0")));
+        assert_eq!(next(&mut scalar, "1", 0, ""), Some(id("1.2.3.0")));
+        assert_eq!(next(&mut scalar, "1.2.3.0", 1, ""), Some(id("1.2.3.0")));
+        assert_eq!(next(&mut scalar, "1.2.3.0", 0, ""), None);
+        assert_eq!(next(&mut scalar, "1.2.3", 0, "1.2.3.0"), None);
+
+        let r = handler::get_next(
+            &mut scalar,
+            &GetNext::new(SearchRangeList(vec![range("1.2.3", 0, "1.2.4")])),
+        );
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![VarBind::new(id("1.2.3.0"), Value::Integer(1))]
+        );
+    }
+
+    #[test]
+    fn scalar_set() {
+        let (mut scalar, value) = writable();
+        let test = |scalar: &mut Scalar, name, data| scalar.test(&VarBind::new(id(name), data));
+
+        assert_eq!(test(&mut scalar, "1.2.3.0", Value::Integer(5)), Ok(()));
+        assert_eq!(
+            test(&mut scalar, "1.2.3.0", Value::Counter32(5)),
+            Err(ResError::WrongType)
+        );
+        assert_eq!(
+            test(&mut scalar, "1.2.3.1", Value::Integer(5)),
+            Err(ResError::NoCreation)
+        );
+        assert_eq!(
+            test(&mut scalar, "1.2.4.0", Value::Integer(5)),
+            Err(ResError::NotWritable)
+        );
+
+        let mut transactions = SetTransactions::new();
+        let vb = VarBindList(vec![VarBind::new(id("1.2.3.0"), Value::Integer(5))]);
+        let r = transactions
+            .dispatch(&mut scalar, &Pdu::from(TestSet::new(vb)))
+            .unwrap();
+        assert_eq!(r.res_error, ResError::NoAgentXError);
+        transactions.dispatch(&mut scalar, &Pdu::from(CommitSet::new()));
+        assert_eq!(*value.lock().unwrap(), 5);
+        transactions.dispatch(&mut scalar, &Pdu::from(UndoSet::new()));
+        assert_eq!(*value.lock().unwrap(), 1);
+        transactions.dispatch(&mut scalar, &Pdu::from(CleanupSet::new()));
+
+        let mut read_only = Scalar::new(id("1.2.3"), || Value::Integer(1)).unwrap();
+        assert!(!read_only.writable());
+        assert_eq!(
+            read_only.test(&VarBind::new(id("1.2.3.0"), Value::Integer(5))),
+            Err(ResError::NotWritable)
+        );
+    }
+}
@@ -0,0 +1,536 @@
+//! Processing of Set requests as defined in [Section 7.2.4](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.4)
+//!
+//! The master agent drives a Set as two-phase commit: a TestSet with all VarBinds, followed by a CommitSet, possibly an
+//! UndoSet if committing failed somewhere, and finally a CleanupSet. [SetTransactions] keeps track of the state of
+//! every transaction and calls the hooks of a [SetHandler] in the right order.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use crate::encodings::{VarBind, VarBindList};
+use crate::pdu::{CleanupSet, CommitSet, Header, Pdu, ResError, Response, TestSet, UndoSet};
+
+/// default time after which a transaction that did not see a CleanupSet is aborted
+pub const DEFAULT_TRANSACTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Write access to the MIB objects of a subagent
+pub trait SetHandler {
+    /// state needed to revert a committed VarBind, e.g., the previous value
+    type Undo;
+
+    /// check if `vb` could be set (TestSet), without changing anything yet. This is also the place to reserve
+    /// resources needed by the commit.
+    fn test(&mut self, vb: &VarBind) -> Result<(), ResError>;
+
+    /// set `vb` (CommitSet), returns what is needed to undo it. Errors are reported to the master agent as
+    /// [ResError::CommitFailed].
+    fn commit(&mut self, vb: &VarBind) -> Result<Self::Undo, ResError>;
+
+    /// revert a committed `vb` (UndoSet). Errors are reported to the master agent as [ResError::UndoFailed].
+    fn undo(&mut self, vb: &VarBind, undo: Self::Undo) -> Result<(), ResError>;
+
+    /// release everything reserved for `vb` (CleanupSet), called for every VarBind of a finished or aborted
+    /// transaction
+    fn cleanup(&mut self, _vb: &VarBind) {}
+}
+
+#[derive(Debug)]
+enum State<U> {
+    // TestSet processed, `false` if a test failed
+    Tested(bool),
+    // CommitSet processed, undo state of the VarBinds that were committed, in order
+    Committed(Vec<U>),
+    // UndoSet processed
+    Undone,
+}
+
+#[derive(Debug)]
+struct Transaction<U> {
+    vb: VarBindList,
+    state: State<U>,
+    started: Instant,
+}
+
+/// State of the Set transactions of a session, keyed by [Header::transaction_id]
+///
+/// PDUs that arrive out of order (e.g., a CommitSet without TestSet or a CommitSet after a failed TestSet) are answered
+/// with [ResError::ProcessingError]. Transactions that do not see a CleanupSet within the timeout are aborted the next
+/// time a TestSet arrives or when [SetTransactions::expire] is called.
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{Value, VarBind, VarBindList, ID};
+/// # use agentx::mib::set::{SetHandler, SetTransactions};
+/// # use agentx::pdu::{CleanupSet, CommitSet, Pdu, ResError, TestSet};
+/// # use std::collections::BTreeMap;
+/// # use std::str::FromStr;
+/// struct Settings(BTreeMap<ID, Value>);
+///
+/// impl SetHandler for Settings {
+///     type Undo = Option<Value>;
+///
+///     fn test(&mut self, vb: &VarBind) -> Result<(), ResError> {
+///         match vb.data {
+///             Value::Integer(_) => Ok(()),
+///             _ => Err(ResError::WrongType),
+///         }
+///     }
+///
+///     fn commit(&mut self, vb: &VarBind) -> Result<Self::Undo, ResError> {
+///         Ok(self.0.insert(vb.name.clone(), vb.data.clone()))
+///     }
+///
+///     fn undo(&mut self, vb: &VarBind, undo: Self::Undo) -> Result<(), ResError> {
+///         match undo {
+///             Some(v) => self.0.insert(vb.name.clone(), v),
+///             None => self.0.remove(&vb.name),
+///         };
+///         Ok(())
+///     }
+/// }
+///
+/// let mut settings = Settings(BTreeMap::new());
+/// let mut transactions = SetTransactions::new();
+/// let id = ID::from_str("1.3.6.1.4.1.23302.2.0").unwrap();
+///
+/// let test = TestSet::new(VarBindList(vec![VarBind::new(id.clone(), Value::Integer(3))]));
+/// let r = transactions.dispatch(&mut settings, &Pdu::from(test)).unwrap();
+/// assert_eq!(r.res_error, ResError::NoAgentXError);
+/// transactions.dispatch(&mut settings, &Pdu::from(CommitSet::new()));
+/// assert!(transactions.dispatch(&mut settings, &Pdu::from(CleanupSet::new())).is_none());
+/// assert_eq!(settings.0.get(&id), Some(&Value::Integer(3)));
+/// ```
+#[derive(Debug)]
+pub struct SetTransactions<U> {
+    transactions: HashMap<u32, Transaction<U>>,
+    timeout: Duration,
+}
+
+impl<U> Default for SetTransactions<U> {
+    fn default() -> Self {
+        Self {
+            transactions: HashMap::new(),
+            timeout: DEFAULT_TRANSACTION_TIMEOUT,
+        }
+    }
+}
+
+fn failed(header: &Header, index: usize, e: ResError) -> Response {
+    let mut response = Response::from_header(header);
+    response.res_error = e;
+    response.res_index = u16::try_from(index + 1).unwrap_or(u16::MAX);
+    response
+}
+
+fn out_of_order(header: &Header) -> Response {
+    let mut response = Response::from_header(header);
+    response.res_error = ResError::ProcessingError;
+    response
+}
+
+impl<U> SetTransactions<U> {
+    /// create an empty SetTransactions with [DEFAULT_TRANSACTION_TIMEOUT]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// create an empty SetTransactions that aborts transactions after `timeout`
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    /// number of transactions that did not see a CleanupSet yet
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns true if there are no open transactions.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// process a TestSet as defined in [Section 7.2.4.1](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.4.1)
+    pub fn test_set<H: SetHandler<Undo = U> + ?Sized>(
+        &mut self,
+        handler: &mut H,
+        pdu: &TestSet,
+    ) -> Response {
+        self.expire(handler);
+
+        let id = pdu.header.transaction_id;
+        if self.transactions.contains_key(&id) {
+            return out_of_order(&pdu.header);
+        }
+
+        let mut response = Response::from_header(&pdu.header);
+        for (i, vb) in pdu.vb.0.iter().enumerate() {
+            if let Err(e) = handler.test(vb) {
+                response = failed(&pdu.header, i, e);
+                break;
+            }
+        }
+
+        self.transactions.insert(
+            id,
+            Transaction {
+                vb: pdu.vb.clone(),
+                state: State::Tested(response.res_error == ResError::NoAgentXError),
+                started: Instant::now(),
+            },
+        );
+
+        response
+    }
+
+    /// process a CommitSet as defined in [Section 7.2.4.2](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.4.2)
+    pub fn commit_set<H: SetHandler<Undo = U> + ?Sized>(
+        &mut self,
+        handler: &mut H,
+        pdu: &CommitSet,
+    ) -> Response {
+        let t = match self.transactions.get_mut(&pdu.header.transaction_id) {
+            Some(t) if matches!(t.state, State::Tested(true)) => t,
+            _ => return out_of_order(&pdu.header),
+        };
+
+        let mut undo = Vec::with_capacity(t.vb.len());
+        let mut response = Response::from_header(&pdu.header);
+        for (i, vb) in t.vb.0.iter().enumerate() {
+            match handler.commit(vb) {
+                Ok(u) => undo.push(u),
+                Err(_) => {
+                    response = failed(&pdu.header, i, ResError::CommitFailed);
+                    break;
+                }
+            }
+        }
+        t.state = State::Committed(undo);
+
+        response
+    }
+
+    /// process an UndoSet as defined in [Section 7.2.4.3](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.4.3)
+    ///
+    /// The committed VarBinds are reverted in reverse order.
+    pub fn undo_set<H: SetHandler<Undo = U> + ?Sized>(
+        &mut self,
+        handler: &mut H,
+        pdu: &UndoSet,
+    ) -> Response {
+        let t = match self.transactions.get_mut(&pdu.header.transaction_id) {
+            Some(t) if matches!(t.state, State::Committed(_)) => t,
+            _ => return out_of_order(&pdu.header),
+        };
+
+        let undo = match std::mem::replace(&mut t.state, State::Undone) {
+            State::Committed(undo) => undo,
+            _ => unreachable!("checked above"),
+        };
+
+        let mut response = Response::from_header(&pdu.header);
+        for (i, (vb, u)) in t.vb.0.iter().zip(undo).enumerate().rev() {
+            if handler.undo(vb, u).is_err() {
+                // keep going, every VarBind that can be reverted should be
+                response = failed(&pdu.header, i, ResError::UndoFailed);
+            }
+        }
+
+        response
+    }
+
+    /// process a CleanupSet as defined in [Section 7.2.4.4](https://datatracker.ietf.org/doc/html/rfc2741#section-7.2.4.4)
+    ///
+    /// There is no Response to a CleanupSet, unknown transactions are ignored.
+    pub fn cleanup_set<H: SetHandler<Undo = U> + ?Sized>(
+        &mut self,
+        handler: &mut H,
+        pdu: &CleanupSet,
+    ) {
+        if let Some(t) = self.transactions.remove(&pdu.header.transaction_id) {
+            cleanup(handler, t);
+        }
+    }
+
+    /// abort transactions that are older than the timeout, their VarBinds are cleaned up but not undone
+    pub fn expire<H: SetHandler<Undo = U> + ?Sized>(&mut self, handler: &mut H) {
+        let timeout = self.timeout;
+        let stale: Vec<u32> = self
+            .transactions
+            .iter()
+            .filter(|(_, t)| t.started.elapsed() >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in stale {
+            if let Some(t) = self.transactions.remove(&id) {
+                cleanup(handler, t);
+            }
+        }
+    }
+
+    /// process a TestSet, CommitSet, UndoSet, or CleanupSet. Returns the Response if there is one, `None` for
+    /// CleanupSet and all other PDUs.
+    pub fn dispatch<H: SetHandler<Undo = U> + ?Sized>(
+        &mut self,
+        handler: &mut H,
+        pdu: &Pdu,
+    ) -> Option<Response> {
+        match pdu {
+            Pdu::TestSet(p) => Some(self.test_set(handler, p)),
+            Pdu::CommitSet(p) => Some(self.commit_set(handler, p)),
+            Pdu::UndoSet(p) => Some(self.undo_set(handler, p)),
+            Pdu::CleanupSet(p) => {
+                self.cleanup_set(handler, p);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn cleanup<H: SetHandler + ?Sized>(handler: &mut H, t: Transaction<H::Undo>) {
+    for vb in &t.vb {
+        handler.cleanup(vb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::id;
+    use crate::encodings::{Value, ID};
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct Settings {
+        values: BTreeMap<ID, Value>,
+        calls: Vec<String>,
+    }
+
+    // only Integers are writable, committing 1.9 fails, undoing 1.8 fails
+    impl SetHandler for Settings {
+        type Undo = Option<Value>;
+
+        fn test(&mut self, vb: &VarBind) -> Result<(), ResError> {
+            self.calls.push(format!("test {}", vb.name));
+            match vb.data {
+                Value::Integer(_) => Ok(()),
+                _ => Err(ResError::WrongType),
+            }
+        }
+
+        fn commit(&mut self, vb: &VarBind) -> Result<Self::Undo, ResError> {
+            self.calls.push(format!("commit {}", vb.name));
+            if vb.name == id("1.9") {
+                return Err(ResError::ResourceUnavailable);
+            }
+            Ok(self.values.insert(vb.name.clone(), vb.data.clone()))
+        }
+
+        fn undo(&mut self, vb: &VarBind, undo: Self::Undo) -> Result<(), ResError> {
+            self.calls.push(format!("undo {}", vb.name));
+            if vb.name == id("1.8") {
+                return Err(ResError::ProcessingError);
+            }
+            match undo {
+                Some(v) => self.values.insert(vb.name.clone(), v),
+                None => self.values.remove(&vb.name),
+            };
+            Ok(())
+        }
+
+        fn cleanup(&mut self, vb: &VarBind) {
+            self.calls.push(format!("cleanup {}", vb.name));
+        }
+    }
+
+    fn testset(transaction_id: u32, vb: Vec<(&str, Value)>) -> Pdu {
+        let mut pdu = TestSet::new(VarBindList(
+            vb.into_iter()
+                .map(|(n, v)| VarBind::new(id(n), v))
+                .collect(),
+        ));
+        pdu.header.transaction_id = transaction_id;
+        pdu.into()
+    }
+
+    fn pdu<P: Into<Pdu>>(p: P, transaction_id: u32) -> Pdu {
+        let mut pdu = p.into();
+        pdu.header_mut().transaction_id = transaction_id;
+        pdu
+    }
+
+    #[test]
+    fn set_commit() {
+        let mut h = Settings::default();
+        h.values.insert(id("1.1"), Value::Integer(0));
+        let mut t = SetTransactions::new();
+
+        let r = t
+            .dispatch(
+                &mut h,
+                &testset(
+                    7,
+                    vec![("1.1", Value::Integer(1)), ("1.2", Value::Integer(2))],
+                ),
+            )
+            .unwrap();
+        assert_eq!(r.header.transaction_id, 7);
+        assert_eq!(r.res_error, ResError::NoAgentXError);
+        assert!(!h.values.contains_key(&id("1.2")));
+
+        let r = t.dispatch(&mut h, &pdu(CommitSet::new(), 7)).unwrap();
+        assert_eq!(r.res_error, ResError::NoAgentXError);
+        assert!(t.dispatch(&mut h, &pdu(CleanupSet::new(), 7)).is_none());
+
+        assert!(t.is_empty());
+        assert_eq!(h.values.get(&id("1.1")), Some(&Value::Integer(1)));
+        assert_eq!(h.values.get(&id("1.2")), Some(&Value::Integer(2)));
+        assert_eq!(
+            h.calls,
+            vec![
+                "test 1.1",
+                "test 1.2",
+                "commit 1.1",
+                "commit 1.2",
+                "cleanup 1.1",
+                "cleanup 1.2"
+            ]
+        );
+    }
+
+    #[test]
+    fn set_test_failed() {
+        let mut h = Settings::default();
+        let mut t = SetTransactions::new();
+
+        let r = t
+            .dispatch(
+                &mut h,
+                &testset(1, vec![("1.1", Value::Integer(1)), ("1.2", Value::Null)]),
+            )
+            .unwrap();
+        assert_eq!(r.res_error, ResError::WrongType);
+        assert_eq!(r.res_index, 2);
+
+        // no commit after a failed test
+        let r = t.dispatch(&mut h, &pdu(CommitSet::new(), 1)).unwrap();
+        assert_eq!(r.res_error, ResError::ProcessingError);
+        assert_eq!(r.res_index, 0);
+        assert!(h.values.is_empty());
+
+        t.dispatch(&mut h, &pdu(CleanupSet::new(), 1));
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn set_commit_failed_undo() {
+        let mut h = Settings::default();
+        h.values.insert(id("1.1"), Value::Integer(0));
+        let mut t = SetTransactions::new();
+
+        t.dispatch(
+            &mut h,
+            &testset(
+                3,
+                vec![
+                    ("1.1", Value::Integer(1)),
+                    ("1.2", Value::Integer(2)),
+                    ("1.9", Value::Integer(9)),
+                ],
+            ),
+        );
+        let r = t.dispatch(&mut h, &pdu(CommitSet::new(), 3)).unwrap();
+        assert_eq!(r.res_error, ResError::CommitFailed);
+        assert_eq!(r.res_index, 3);
+
+        let r = t.dispatch(&mut h, &pdu(UndoSet::new(), 3)).unwrap();
+        assert_eq!(r.res_error, ResError::NoAgentXError);
+        assert_eq!(h.values.get(&id("1.1")), Some(&Value::Integer(0)));
+        assert!(!h.values.contains_key(&id("1.2")));
+
+        // undo only once
+        let r = t.dispatch(&mut h, &pdu(UndoSet::new(), 3)).unwrap();
+        assert_eq!(r.res_error, ResError::ProcessingError);
+
+        t.dispatch(&mut h, &pdu(CleanupSet::new(), 3));
+        assert_eq!(
+            h.calls[6..].to_vec(),
+            vec![
+                "undo 1.2",
+                "undo 1.1",
+                "cleanup 1.1",
+                "cleanup 1.2",
+                "cleanup 1.9"
+            ]
+        );
+    }
+
+    #[test]
+    fn set_undo_failed() {
+        let mut h = Settings::default();
+        let mut t = SetTransactions::new();
+
+        t.dispatch(
+            &mut h,
+            &testset(
+                4,
+                vec![("1.7", Value::Integer(7)), ("1.8", Value::Integer(8))],
+            ),
+        );
+        t.dispatch(&mut h, &pdu(CommitSet::new(), 4));
+        let r = t.dispatch(&mut h, &pdu(UndoSet::new(), 4)).unwrap();
+        assert_eq!(r.res_error, ResError::UndoFailed);
+        assert_eq!(r.res_index, 2);
+        // the other VarBind is reverted nevertheless
+        assert!(!h.values.contains_key(&id("1.7")));
+    }
+
+    #[test]
+    fn set_out_of_order() {
+        let mut h = Settings::default();
+        let mut t = SetTransactions::new();
+
+        for p in vec![pdu(CommitSet::new(), 5), pdu(UndoSet::new(), 5)] {
+            let r = t.dispatch(&mut h, &p).unwrap();
+            assert_eq!(r.res_error, ResError::ProcessingError);
+        }
+        assert!(t.dispatch(&mut h, &pdu(CleanupSet::new(), 5)).is_none());
+
+        // undo before commit
+        t.dispatch(&mut h, &testset(5, vec![("1.1", Value::Integer(1))]));
+        let r = t.dispatch(&mut h, &pdu(UndoSet::new(), 5)).unwrap();
+        assert_eq!(r.res_error, ResError::ProcessingError);
+
+        // duplicate TestSet
+        let r = t
+            .dispatch(&mut h, &testset(5, vec![("1.1", Value::Integer(1))]))
+            .unwrap();
+        assert_eq!(r.res_error, ResError::ProcessingError);
+        assert!(h.calls.iter().all(|c| c.starts_with("test")));
+    }
+
+    #[test]
+    fn set_expire() {
+        let mut h = Settings::default();
+        let mut t = SetTransactions::with_timeout(Duration::from_secs(0));
+
+        t.dispatch(&mut h, &testset(1, vec![("1.1", Value::Integer(1))]));
+        assert_eq!(t.len(), 1);
+        // the stale transaction 1 is aborted by the next one
+        t.dispatch(&mut h, &testset(2, vec![("1.2", Value::Integer(2))]));
+        assert_eq!(t.len(), 1);
+        assert!(h.calls.contains(&"cleanup 1.1".to_string()));
+
+        let r = t.dispatch(&mut h, &pdu(CommitSet::new(), 1)).unwrap();
+        assert_eq!(r.res_error, ResError::ProcessingError);
+
+        t.expire(&mut h);
+        assert!(t.is_empty());
+    }
+}
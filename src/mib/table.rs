@@ -0,0 +1,407 @@
+//! Conceptual tables as defined in [RFC 2578 Section 7.1.12](https://datatracker.ietf.org/doc/html/rfc2578#section-7.1.12)
+//!
+//! A [Table] holds typed rows keyed by their index values and a value accessor per column. The object instances of
+//! the table are `entry.column.index`, walked column by column in lexicographical order. Cells for which the accessor
+//! returns `None` are absent and skipped by GetNext, a column without any present cell is skipped entirely.
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::fmt;
+use std::ops::Bound;
+
+use crate::encodings::id::MAX_SUB_IDS;
+use crate::encodings::{SearchRange, Value, VarBind, ID};
+use crate::mib::index::{self, IndexError, IndexType};
+use crate::mib::MibHandler;
+use crate::pdu::ResError;
+
+type Column<R> = dyn Fn(&R) -> Option<Value> + Send + Sync;
+
+/// Conceptual table of rows of type `R`
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{OctetString, SearchRange, Value, ID};
+/// # use agentx::mib::{IndexType, Table};
+/// # use std::str::FromStr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// struct Interface {
+///     name: String,
+///     mtu: Option<i32>,
+/// }
+///
+/// let mut table = Table::new(ID::from_str("1.3.6.1.2.1.2.2.1")?, vec![IndexType::Integer])
+///     .column(2, |i: &Interface| Some(Value::OctetString(OctetString::from(i.name.as_str()))))
+///     .column(4, |i: &Interface| i.mtu.map(Value::Integer));
+/// table.insert(&[Value::Integer(1)], Interface { name: "lo".into(), mtu: None })?;
+/// table.insert(&[Value::Integer(2)], Interface { name: "eth0".into(), mtu: Some(1500) })?;
+///
+/// // the last ifDescr is followed by the first present ifMtu
+/// let range = SearchRange::new(ID::from_str("1.3.6.1.2.1.2.2.1.2.2")?, ID::default());
+/// let vb = table.next(&range).expect("successor");
+/// assert_eq!(vb.name, ID::from_str("1.3.6.1.2.1.2.2.1.4.2")?);
+/// assert_eq!(vb.data, Value::Integer(1500));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Table<R> {
+    entry: ID,
+    index: Vec<IndexType>,
+    columns: BTreeMap<u32, Box<Column<R>>>,
+    // keyed by the encoded index, the order of the sub-identifiers is the order of the instances
+    rows: BTreeMap<Vec<u32>, R>,
+}
+
+impl<R> Table<R> {
+    /// create an empty table below the OID of its conceptual row `entry` (e.g. `ifEntry`), with the INDEX syntax
+    /// `index`
+    pub fn new(mut entry: ID, index: Vec<IndexType>) -> Self {
+        entry.include = 0;
+        Self {
+            entry,
+            index,
+            columns: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// add the column `column`, `value` returns the value of a cell or `None` if it is absent. Replaces an existing
+    /// column with the same number.
+    pub fn column<F>(mut self, column: u32, value: F) -> Self
+    where
+        F: Fn(&R) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.columns.insert(column, Box::new(value));
+        self
+    }
+
+    /// OID of the conceptual row
+    pub fn entry(&self) -> &ID {
+        &self.entry
+    }
+
+    /// INDEX syntax of the table
+    pub fn index(&self) -> &[IndexType] {
+        &self.index
+    }
+
+    fn key(&self, index: &[Value]) -> Result<Vec<u32>, IndexError> {
+        let key = index::encode(&self.index, index)?;
+        // entry.column.key has to be a valid OID
+        if self.entry.len() + 1 + key.len() > MAX_SUB_IDS {
+            return Err(IndexError::TooLong);
+        }
+        Ok(key)
+    }
+
+    /// insert a row, returns the previous row with the same index values
+    pub fn insert(&mut self, index: &[Value], row: R) -> Result<Option<R>, IndexError> {
+        let key = self.key(index)?;
+        Ok(self.rows.insert(key, row))
+    }
+
+    /// remove a row, returns it if it was present
+    pub fn remove(&mut self, index: &[Value]) -> Result<Option<R>, IndexError> {
+        let key = self.key(index)?;
+        Ok(self.rows.remove(&key))
+    }
+
+    /// row with the index values `index`
+    pub fn row(&self, index: &[Value]) -> Result<Option<&R>, IndexError> {
+        let key = self.key(index)?;
+        Ok(self.rows.get(&key))
+    }
+
+    /// mutable row with the index values `index`
+    pub fn row_mut(&mut self, index: &[Value]) -> Result<Option<&mut R>, IndexError> {
+        let key = self.key(index)?;
+        Ok(self.rows.get_mut(&key))
+    }
+
+    /// all rows and their index values in lexicographical order
+    pub fn rows(&self) -> Rows<'_, R> {
+        Rows {
+            index: &self.index,
+            iter: self.rows.iter(),
+        }
+    }
+
+    /// number of rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// remove all rows
+    pub fn clear(&mut self) {
+        self.rows.clear()
+    }
+
+    /// replace all rows by a new snapshot, the table is left untouched if an index can not be encoded
+    pub fn replace<I: IntoIterator<Item = (Vec<Value>, R)>>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), IndexError> {
+        let mut rows = BTreeMap::new();
+        for (index, row) in iter {
+            rows.insert(self.key(&index)?, row);
+        }
+        self.rows = rows;
+        Ok(())
+    }
+
+    fn instance(&self, column: u32, key: &[u32]) -> ID {
+        let mut id = self.entry.clone();
+        id.append(&[column])
+            .and_then(|_| id.append(key))
+            .expect("the length of instances is checked on insert");
+        id
+    }
+
+    /// value of the object instance `id`, [Value::NoSuchObject] if `id` is not below a column and
+    /// [Value::NoSuchInstance] if the row or the cell is absent
+    pub fn value(&self, id: &ID) -> Value {
+        match id.strip_prefix(&self.entry) {
+            Some([column, key @ ..]) => match self.columns.get(column) {
+                Some(value) => self
+                    .rows
+                    .get(key)
+                    .and_then(value)
+                    .unwrap_or(Value::NoSuchInstance),
+                None => Value::NoSuchObject,
+            },
+            _ => Value::NoSuchObject,
+        }
+    }
+
+    /// the first present cell within `range` in lexicographical order. The start of the range is only included if
+    /// its `include` is set, the end is never included.
+    pub fn next(&self, range: &SearchRange) -> Option<VarBind> {
+        if self.rows.is_empty() {
+            return None;
+        }
+
+        let start = &range.start;
+        let (first, row) = match start.strip_prefix(&self.entry) {
+            Some([]) => (0, Bound::Unbounded),
+            Some([column, key @ ..]) if start.include != 0 => (*column, Bound::Included(key)),
+            Some([column, key @ ..]) => (*column, Bound::Excluded(key)),
+            None if *start < self.entry => (0, Bound::Unbounded),
+            None => return None,
+        };
+
+        for (column, value) in self.columns.range(first..) {
+            let rows = if *column == first {
+                (row, Bound::Unbounded)
+            } else {
+                (Bound::Unbounded, Bound::Unbounded)
+            };
+
+            // every instance of this and the following columns is past the end
+            if !range.end.is_null() && self.instance(*column, &[]) >= range.end {
+                return None;
+            }
+
+            for (key, r) in self.rows.range::<[u32], _>(rows) {
+                if let Some(data) = value(r) {
+                    return Some(VarBind::new(self.instance(*column, key), data))
+                        .filter(|vb| range.end.is_null() || vb.name < range.end);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for Table<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Table")
+            .field("entry", &self.entry)
+            .field("index", &self.index)
+            .field("columns", &self.columns.keys().collect::<Vec<_>>())
+            .field("rows", &self.rows)
+            .finish()
+    }
+}
+
+impl<R> MibHandler for Table<R> {
+    fn get(&mut self, id: &ID) -> Result<Value, ResError> {
+        Ok(self.value(id))
+    }
+
+    fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+        Ok(self.next(range))
+    }
+}
+
+/// Iterator over the rows of a [Table] and their index values
+pub struct Rows<'a, R> {
+    index: &'a [IndexType],
+    iter: btree_map::Iter<'a, Vec<u32>, R>,
+}
+
+impl<'a, R> Iterator for Rows<'a, R> {
+    type Item = (Vec<Value>, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, row)| {
+            let index = index::decode(self.index, key).expect("keys are encoded index values");
+            (index, row)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::{id, range};
+    use crate::encodings::{OctetString, SearchRangeList};
+    use crate::mib::handler;
+    use crate::pdu::{Get, GetBulk};
+
+    fn vb(name: &str, data: Value) -> VarBind {
+        VarBind::new(id(name), data)
+    }
+
+    // (name, optional counter)
+    fn table() -> Table<(&'static str, Option<u32>)> {
+        let mut table = Table::new(id("1.2.1"), vec![IndexType::OctetString])
+            .column(2, |r: &(&str, Option<u32>)| {
+                Some(Value::OctetString(OctetString::from(r.0)))
+            })
+            .column(3, |_: &(&str, Option<u32>)| None)
+            .column(5, |r: &(&str, Option<u32>)| r.1.map(Value::Counter32));
+        table
+            .replace(vec![
+                (
+                    vec![Value::OctetString(OctetString::from("b"))],
+                    ("b", None),
+                ),
+                (
+                    vec![Value::OctetString(OctetString::from("a"))],
+                    ("a", Some(1)),
+                ),
+                (
+                    vec![Value::OctetString(OctetString::from("cc"))],
+                    ("cc", Some(3)),
+                ),
+            ])
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn table_rows() {
+        let mut table = table();
+        assert_eq!(table.len(), 3);
+
+        let names: Vec<&str> = table.rows().map(|(_, r)| r.0).collect();
+        assert_eq!(names, vec!["a", "b", "cc"]);
+        let (index, _) = table.rows().next().unwrap();
+        assert_eq!(index, vec![Value::OctetString(OctetString::from("a"))]);
+
+        let b = [Value::OctetString(OctetString::from("b"))];
+        table.row_mut(&b).unwrap().unwrap().1 = Some(2);
+        assert_eq!(table.row(&b), Ok(Some(&("b", Some(2)))));
+        assert_eq!(table.remove(&b), Ok(Some(("b", Some(2)))));
+        assert_eq!(table.row(&b), Ok(None));
+
+        assert_eq!(
+            table.insert(&[Value::Integer(1)], ("x", None)),
+            Err(IndexError::WrongType { position: 0 })
+        );
+        assert_eq!(
+            table.replace(vec![(vec![], ("x", None))]),
+            Err(IndexError::Count {
+                expected: 1,
+                got: 0
+            })
+        );
+        assert_eq!(table.len(), 2);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn table_value() {
+        let table = table();
+
+        assert_eq!(
+            table.value(&id("1.2.1.2.1.97")),
+            Value::OctetString(OctetString::from("a"))
+        );
+        assert_eq!(table.value(&id("1.2.1.5.1.97")), Value::Counter32(1));
+        // absent cell, absent row, malformed index
+        assert_eq!(table.value(&id("1.2.1.5.1.98")), Value::NoSuchInstance);
+        assert_eq!(table.value(&id("1.2.1.2.1.100")), Value::NoSuchInstance);
+        assert_eq!(table.value(&id("1.2.1.2.1")), Value::NoSuchInstance);
+        // no such column
+        assert_eq!(table.value(&id("1.2.1.4.1.97")), Value::NoSuchObject);
+        assert_eq!(table.value(&id("1.2.1")), Value::NoSuchObject);
+        assert_eq!(table.value(&id("1.2.2.2.1.97")), Value::NoSuchObject);
+    }
+
+    #[test]
+    fn table_next() {
+        let table = table();
+        let next = |start, include, end| table.next(&range(start, include, end)).map(|vb| vb.name);
+
+        // before, at, and within the table
+        assert_eq!(next("1", 0, ""), Some(id("1.2.1.2.1.97")));
+        assert_eq!(next("1.2.1", 0, ""), Some(id("1.2.1.2.1.97")));
+        assert_eq!(next("1.2.1.2.1.97", 0, ""), Some(id("1.2.1.2.1.98")));
+        assert_eq!(next("1.2.1.2.1.97", 1, ""), Some(id("1.2.1.2.1.97")));
+        assert_eq!(next("1.2.1.2.1.97.0", 0, ""), Some(id("1.2.1.2.1.98")));
+        // end of a column, empty column 3, absent cell "b" in column 5
+        assert_eq!(next("1.2.1.2.2.99.99", 0, ""), Some(id("1.2.1.5.1.97")));
+        assert_eq!(next("1.2.1.3", 0, ""), Some(id("1.2.1.5.1.97")));
+        assert_eq!(next("1.2.1.5.1.97", 0, ""), Some(id("1.2.1.5.2.99.99")));
+        // end of the table and range end
+        assert_eq!(next("1.2.1.5.2.99.99", 0, ""), None);
+        assert_eq!(next("1.2.2", 0, ""), None);
+        assert_eq!(next("1.2.1.2.2.99.99", 0, "1.2.1.5"), None);
+        assert_eq!(next("1.2.1.2.1.97", 0, "1.2.1.2.1.98"), None);
+        assert_eq!(
+            next("1.2.1.5.1.97", 0, "1.2.1.5.2.99.100"),
+            Some(id("1.2.1.5.2.99.99"))
+        );
+    }
+
+    #[test]
+    fn table_handler() {
+        let mut table = table();
+
+        let r = handler::get(
+            &mut table,
+            &Get::new(SearchRangeList(vec![
+                range("1.2.1.5.1.97", 0, ""),
+                range("1.2.1.5.1.98", 0, ""),
+            ])),
+        );
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                vb("1.2.1.5.1.97", Value::Counter32(1)),
+                vb("1.2.1.5.1.98", Value::NoSuchInstance),
+            ]
+        );
+
+        let mut pdu = GetBulk::new(SearchRangeList(vec![range("1.2.1.2.2.99.99", 0, "1.2.2")]));
+        pdu.max_repetitions = 4;
+        let r = handler::get_bulk(&mut table, &pdu);
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                vb("1.2.1.5.1.97", Value::Counter32(1)),
+                vb("1.2.1.5.2.99.99", Value::Counter32(3)),
+                vb("1.2.1.5.2.99.99", Value::EndOfMibView),
+            ]
+        );
+    }
+}
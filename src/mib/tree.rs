@@ -0,0 +1,270 @@
+//! Sorted in-memory tree of object instances
+//!
+//! An [OidTree] holds a snapshot of values keyed by their ID and answers Get and GetNext lookups on it. It implements
+//! [MibHandler], so a subagent that periodically publishes its metrics only has to keep the tree up to date.
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::iter::FromIterator;
+use std::ops::Bound;
+
+use crate::encodings::{SearchRange, Value, VarBind, ID};
+use crate::mib::MibHandler;
+use crate::pdu::ResError;
+
+/// Sorted map of object instances to their values
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::encodings::{SearchRange, Value, ID};
+/// # use agentx::mib::OidTree;
+/// # use std::str::FromStr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut tree = OidTree::new();
+/// tree.insert(ID::from_str("1.3.6.1.4.1.23302.1.1.0")?, Value::Integer(1));
+/// tree.insert(ID::from_str("1.3.6.1.4.1.23302.1.2.0")?, Value::Integer(2));
+///
+/// let range = SearchRange::new(ID::from_str("1.3.6.1.4.1.23302.1.1.0")?, ID::default());
+/// let (id, value) = tree.next(&range).expect("successor");
+/// assert_eq!(*id, ID::from_str("1.3.6.1.4.1.23302.1.2.0")?);
+/// assert_eq!(*value, Value::Integer(2));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OidTree {
+    map: BTreeMap<ID, Value>,
+}
+
+impl OidTree {
+    /// create an empty OidTree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// insert a value, returns the previous value of `id`
+    pub fn insert(&mut self, mut id: ID, value: Value) -> Option<Value> {
+        // include is a property of SearchRanges, not of object instances
+        id.include = 0;
+        self.map.insert(id, value)
+    }
+
+    /// remove a value, returns it if it was present
+    pub fn remove(&mut self, id: &ID) -> Option<Value> {
+        self.map.remove(id)
+    }
+
+    /// value of `id`
+    pub fn get(&self, id: &ID) -> Option<&Value> {
+        self.map.get(id)
+    }
+
+    /// Returns true if there is a value for `id`.
+    pub fn contains(&self, id: &ID) -> bool {
+        self.map.contains_key(id)
+    }
+
+    /// number of object instances
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if there are no object instances.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// remove all object instances
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// the first object instance within `range` in lexicographical order. The start of the range is only included if
+    /// its `include` is set, the end is never included.
+    pub fn next(&self, range: &SearchRange) -> Option<(&ID, &Value)> {
+        let start = if range.start.include != 0 {
+            Bound::Included(&range.start)
+        } else {
+            Bound::Excluded(&range.start)
+        };
+
+        self.map
+            .range::<ID, _>((start, Bound::Unbounded))
+            .next()
+            .filter(|(id, _)| range.end.is_null() || **id < range.end)
+    }
+
+    /// all object instances in lexicographical order
+    pub fn iter(&self) -> btree_map::Iter<'_, ID, Value> {
+        self.map.iter()
+    }
+
+    /// object instances that are equal to or below `prefix`, in lexicographical order
+    pub fn subtree<'a>(&'a self, prefix: &'a ID) -> impl Iterator<Item = (&'a ID, &'a Value)> + 'a {
+        self.map
+            .range::<ID, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(id, _)| id.starts_with(prefix))
+    }
+
+    /// replace all object instances by a new snapshot
+    pub fn replace<I: IntoIterator<Item = (ID, Value)>>(&mut self, iter: I) {
+        self.clear();
+        self.extend(iter);
+    }
+
+    /// replace the object instances that are equal to or below `prefix` by a new snapshot, instances outside of the
+    /// subtree are left untouched
+    pub fn replace_subtree<I: IntoIterator<Item = (ID, Value)>>(&mut self, prefix: &ID, iter: I) {
+        let old: Vec<ID> = self.subtree(prefix).map(|(id, _)| id.clone()).collect();
+        for id in old {
+            self.map.remove(&id);
+        }
+        self.extend(iter);
+    }
+}
+
+impl Extend<(ID, Value)> for OidTree {
+    fn extend<I: IntoIterator<Item = (ID, Value)>>(&mut self, iter: I) {
+        for (id, value) in iter {
+            self.insert(id, value);
+        }
+    }
+}
+
+impl FromIterator<(ID, Value)> for OidTree {
+    fn from_iter<I: IntoIterator<Item = (ID, Value)>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<'a> IntoIterator for &'a OidTree {
+    type Item = (&'a ID, &'a Value);
+    type IntoIter = btree_map::Iter<'a, ID, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+impl MibHandler for OidTree {
+    fn get(&mut self, id: &ID) -> Result<Value, ResError> {
+        Ok(OidTree::get(self, id)
+            .cloned()
+            .unwrap_or(Value::NoSuchObject))
+    }
+
+    fn get_next(&mut self, range: &SearchRange) -> Result<Option<VarBind>, ResError> {
+        Ok(self
+            .next(range)
+            .map(|(id, value)| VarBind::new(id.clone(), value.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::tests::{id, range};
+    use crate::encodings::SearchRangeList;
+    use crate::mib::handler;
+    use crate::pdu::{Get, GetNext};
+
+    fn tree() -> OidTree {
+        vec![
+            (id("1.2.1.0"), Value::Integer(1)),
+            (id("1.2.2.1"), Value::Integer(21)),
+            (id("1.2.2.2"), Value::Integer(22)),
+            (id("1.2.3.0"), Value::Integer(3)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn tree_insert_remove() {
+        let mut tree = tree();
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.get(&id("1.2.1.0")), Some(&Value::Integer(1)));
+        assert_eq!(
+            tree.insert(id("1.2.1.0"), Value::Integer(10)),
+            Some(Value::Integer(1))
+        );
+        assert_eq!(tree.remove(&id("1.2.1.0")), Some(Value::Integer(10)));
+        assert!(!tree.contains(&id("1.2.1.0")));
+        assert_eq!(tree.remove(&id("1.2.1.0")), None);
+        tree.clear();
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn tree_next() {
+        let tree = tree();
+        let next = |start, include, end| {
+            tree.next(&range(start, include, end))
+                .map(|(id, _)| id.clone())
+        };
+
+        assert_eq!(next("1.2.1.0", 0, ""), Some(id("1.2.2.1")));
+        assert_eq!(next("1.2.1.0", 1, ""), Some(id("1.2.1.0")));
+        assert_eq!(next("1.2.2", 0, ""), Some(id("1.2.2.1")));
+        assert_eq!(next("1", 0, ""), Some(id("1.2.1.0")));
+        assert_eq!(next("1.2.2.2", 0, "1.2.3.0"), None);
+        assert_eq!(next("1.2.2.2", 0, "1.2.3.1"), Some(id("1.2.3.0")));
+        assert_eq!(next("1.2.3.0", 0, ""), None);
+    }
+
+    #[test]
+    fn tree_subtree() {
+        let tree = tree();
+        let ids: Vec<ID> = tree
+            .subtree(&id("1.2.2"))
+            .map(|(id, _)| id.clone())
+            .collect();
+        assert_eq!(ids, vec![id("1.2.2.1"), id("1.2.2.2")]);
+        assert_eq!(tree.subtree(&id("1.2.2.1")).count(), 1);
+        assert_eq!(tree.subtree(&id("1.3")).count(), 0);
+        assert_eq!(tree.subtree(&ID::default()).count(), 4);
+    }
+
+    #[test]
+    fn tree_replace() {
+        let mut tree = tree();
+        tree.replace_subtree(&id("1.2.2"), vec![(id("1.2.2.3"), Value::Integer(23))]);
+        let ids: Vec<ID> = tree.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(ids, vec![id("1.2.1.0"), id("1.2.2.3"), id("1.2.3.0")]);
+
+        tree.replace(vec![(id("1.3"), Value::Null)]);
+        assert_eq!(tree.len(), 1);
+        assert!(tree.contains(&id("1.3")));
+    }
+
+    #[test]
+    fn tree_handler() {
+        let mut tree = tree();
+
+        let r = handler::get(
+            &mut tree,
+            &Get::new(SearchRangeList(vec![
+                range("1.2.1.0", 0, ""),
+                range("1.2.2", 0, ""),
+            ])),
+        );
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![
+                VarBind::new(id("1.2.1.0"), Value::Integer(1)),
+                VarBind::new(id("1.2.2"), Value::NoSuchObject),
+            ]
+        );
+
+        let r = handler::get_next(
+            &mut tree,
+            &GetNext::new(SearchRangeList(vec![range("1.2.2.2", 0, "")])),
+        );
+        assert_eq!(
+            r.vb.unwrap().0,
+            vec![VarBind::new(id("1.2.3.0"), Value::Integer(3))]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Sending notifications (traps) as described in [Section 6.2.10](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.10)
+//!
+//! The VarBindList of a Notify PDU has to start with `sysUpTime.0` (optional) and `snmpTrapOID.0`, followed by the
+//! payload of the notification. A [Notification] takes care of that and is sent with [crate::session::Session::notify].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use agentx::encodings::{ID, Value, VarBind};
+//! # use agentx::notification::Notification;
+//! # use agentx::pdu::Open;
+//! # use agentx::session::Session;
+//! # use agentx::transport::Transport;
+//! # use std::str::FromStr;
+//! # use std::time::Duration;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let transport = Transport::connect("/var/agentx/master")?;
+//! let open = Open::new(ID::from_str("1.3.6.1.4.1.23302")?, "my subagent");
+//! let mut session = Session::open(transport, open)?;
+//!
+//! let notification = Notification::new(ID::from_str("1.3.6.1.4.1.23302.0.1")?)
+//!     .sys_uptime(Duration::from_secs(42))
+//!     .varbind(VarBind::new(ID::from_str("1.3.6.1.4.1.23302.1.1.0")?, Value::Integer(1)));
+//! session.notify(notification)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::encodings::{Context, Value, VarBind, VarBindList, ID};
+use crate::pdu::Notify;
+
+/// sub-identifiers of `sysUpTime.0`
+pub const SYS_UPTIME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 3, 0];
+/// sub-identifiers of `snmpTrapOID.0`
+pub const SNMP_TRAP_OID: &[u32] = &[1, 3, 6, 1, 6, 3, 1, 1, 4, 1, 0];
+
+fn id(sub_ids: &[u32]) -> ID {
+    ID::try_from(sub_ids.to_vec()).expect("well-known OIDs are valid")
+}
+
+/// A notification, i.e., the trap OID and its payload VarBinds
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Notification {
+    trap: ID,
+    sys_uptime: Option<Duration>,
+    context: Option<Context>,
+    vb: Vec<VarBind>,
+}
+
+impl Notification {
+    /// create a new notification for the trap `trap` (the value of `snmpTrapOID.0`)
+    pub fn new(trap: ID) -> Self {
+        Self {
+            trap,
+            sys_uptime: None,
+            context: None,
+            vb: Vec::new(),
+        }
+    }
+
+    /// include `sysUpTime.0`, serialized in 100th of seconds
+    pub fn sys_uptime(mut self, uptime: Duration) -> Self {
+        self.sys_uptime = Some(uptime);
+        self
+    }
+
+    /// send the notification in a non-default context
+    pub fn context(mut self, context: Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// append a payload VarBind
+    pub fn varbind(mut self, vb: VarBind) -> Self {
+        self.vb.push(vb);
+        self
+    }
+
+    /// append payload VarBinds
+    pub fn varbinds<I: IntoIterator<Item = VarBind>>(mut self, vb: I) -> Self {
+        self.vb.extend(vb);
+        self
+    }
+
+    /// build the Notify PDU. `sysUpTime.0` wraps around like the TimeTicks counter it is, after about 497 days.
+    pub fn to_notify(&self) -> Notify {
+        let mut vb = Vec::with_capacity(self.vb.len() + 2);
+
+        if let Some(uptime) = self.sys_uptime {
+            // TimeTicks are unsigned, stored in the bits of the i32
+            let ticks = (uptime.as_millis() / 10 % (1 << 32)) as u32 as i32;
+            vb.push(VarBind::new(id(SYS_UPTIME), Value::TimeTicks(ticks)));
+        }
+        vb.push(VarBind::new(
+            id(SNMP_TRAP_OID),
+            Value::ObjectIdentifier(self.trap.clone()),
+        ));
+        vb.extend(self.vb.iter().cloned());
+
+        let mut notify = Notify::new(VarBindList(vb));
+        notify.context = self.context.clone();
+
+        notify
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::OctetString;
+    use std::str::FromStr;
+
+    #[test]
+    fn notification_varbinds() {
+        let trap = ID::from_str("1.2.3").unwrap();
+        let payload = VarBind::new(ID::from_str("1.2.4").unwrap(), Value::Integer(1));
+
+        let notify = Notification::new(trap.clone())
+            .varbind(payload.clone())
+            .to_notify();
+        assert_eq!(
+            notify.vb.0,
+            vec![
+                VarBind::new(id(SNMP_TRAP_OID), Value::ObjectIdentifier(trap.clone())),
+                payload.clone(),
+            ]
+        );
+        assert!(notify.context.is_none());
+
+        let notify = Notification::new(trap.clone())
+            .sys_uptime(Duration::from_secs(3))
+            .context(Context(OctetString::new("ctx")))
+            .varbinds(vec![payload.clone(), payload.clone()])
+            .to_notify();
+        assert_eq!(notify.vb.len(), 4);
+        assert_eq!(
+            notify.vb.0[0],
+            VarBind::new(
+                ID::from_str("1.3.6.1.2.1.1.3.0").unwrap(),
+                Value::TimeTicks(300)
+            )
+        );
+        assert_eq!(
+            notify.vb.0[1].name,
+            ID::from_str("1.3.6.1.6.3.1.1.4.1.0").unwrap()
+        );
+        assert_eq!(notify.context, Some(Context(OctetString::new("ctx"))));
+    }
+
+    #[test]
+    fn notification_uptime_wrap() {
+        let uptime = |days: u64| {
+            let notify = Notification::new(ID::from_str("1.2.3").unwrap())
+                .sys_uptime(Duration::from_secs(days * 24 * 3600))
+                .to_notify();
+            notify.vb.0[0].data.clone()
+        };
+
+        // beyond i32::MAX, but still within the unsigned TimeTicks
+        assert_eq!(uptime(365), Value::TimeTicks(3_153_600_000u32 as i32));
+        // 4_320_000_000 ticks wrap around 2^32
+        assert_eq!(uptime(500), Value::TimeTicks(25_032_704));
+    }
+}
@@ -1,14 +1,21 @@
 //! PDU protocol definitions as defined in [Section 6](https://datatracker.ietf.org/doc/html/rfc2741#section-6)
 //!
 //! Note that Header.payload_length is only calculated when necessary, which is when a struct (e.g., Open PDU) is serialized or deserialized.
+//! The same goes for the NON_DEFAULT_CONTEXT flag, which is derived from the PDU's `context` when it is serialized.
+//! Serializing does not modify the PDU, so both are written to the bytes but not stored in the Header, use
+//! `update_header()` (or `update_header_with()` for non-default [EncodeOptions]) to store them.
 
 use std::convert::TryFrom;
-use std::io::{Error, ErrorKind};
+use std::io::{self, Write};
 use std::mem::size_of;
 use std::time::Duration;
 
-use crate::encodings::{Context, OctetString, SearchRangeList, VarBindList, ID};
-use crate::{bytes_to_u16, bytes_to_u32, u16_to_bytes, u32_to_bytes, ByteOrder};
+use crate::encodings::{Context, OctetString, OidRange, SearchRangeList, VarBindList, ID};
+use crate::error::ResultExt;
+use crate::{
+    bytes_to_u16, bytes_to_u32, skip, truncated, u16_to_bytes, u32_to_bytes, ByteOrder,
+    EncodeOptions, Error,
+};
 
 /// PDU Header as defined in [Section 6.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, header with a "random" default type does not make sense.
@@ -17,8 +24,8 @@ pub struct Header {
     pub version: u8,
     /// PDU type
     pub ty: Type, // low level u8
-    /// flags (INSTANCE_REGISTRATION, NEW_INDEX, ANY_INDEX, NON_DEFAULT_CONTEXT, NON_DEFAULT_CONTEXT), 5-7 reserved
-    pub flags: u8,
+    /// flags (INSTANCE_REGISTRATION, NEW_INDEX, ANY_INDEX, NON_DEFAULT_CONTEXT, NETWORK_BYTE_ORDER), 5-7 reserved
+    pub flags: Flags,
     // reserved: u8
     /// session ID
     pub session_id: u32,
@@ -30,7 +37,7 @@ pub struct Header {
     pub payload_length: u32, // 0 or multiple of 4
 }
 
-// bitmask
+// bit positions, see Flags for named accessors
 /// used in the Register PDU
 pub const INSTANCE_REGISTRATION: u8 = 0;
 /// used in IndexAllocate and IndexDeallocate PDUs.
@@ -42,34 +49,306 @@ pub const NON_DEFAULT_CONTEXT: u8 = 3;
 /// applies to all multi-byte integer values including header fields, if set BigEndian.
 pub const NETWORK_BYTE_ORDER: u8 = 4;
 
-fn is_set(flags: u8, mask: u8) -> bool {
-    flags & mask == mask
+// bits 5-7 are reserved and have to be 0
+const RESERVED_FLAGS: u8 = 0b1110_0000;
+
+/// Header flags as defined in [Section 6.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1)
+///
+/// The NON_DEFAULT_CONTEXT flag is set by the PDUs' `to_bytes()` according to their `context`, there is no need to
+/// set it manually.
+///
+/// # Examples
+///
+/// ```
+/// # use agentx::pdu::Flags;
+/// let mut flags = Flags::new();
+/// flags.set_network_byte_order(true);
+/// assert!(flags.network_byte_order());
+/// assert_eq!(flags.bits(), 0x10);
+///
+/// assert!(Flags::from_bits(0x80).is_err());
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    /// create Flags with no flag set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// create Flags from their wire representation, fails if a reserved bit is set
+    pub fn from_bits(bits: u8) -> Result<Self, Error> {
+        if bits & RESERVED_FLAGS != 0 {
+            return Err(Error::ReservedFlags {
+                offset: 0,
+                flags: bits,
+            });
+        }
+        Ok(Self(bits))
+    }
+
+    /// wire representation
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    fn is_set(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    fn set(&mut self, bit: u8, value: bool) {
+        if value {
+            self.0 |= 1 << bit;
+        } else {
+            self.0 &= !(1 << bit);
+        }
+    }
+
+    /// INSTANCE_REGISTRATION, used in the Register PDU
+    pub fn instance_registration(&self) -> bool {
+        self.is_set(INSTANCE_REGISTRATION)
+    }
+
+    /// set INSTANCE_REGISTRATION
+    pub fn set_instance_registration(&mut self, value: bool) {
+        self.set(INSTANCE_REGISTRATION, value)
+    }
+
+    /// NEW_INDEX, used in IndexAllocate and IndexDeallocate PDUs
+    pub fn new_index(&self) -> bool {
+        self.is_set(NEW_INDEX)
+    }
+
+    /// set NEW_INDEX
+    pub fn set_new_index(&mut self, value: bool) {
+        self.set(NEW_INDEX, value)
+    }
+
+    /// ANY_INDEX, used in IndexAllocate and IndexDeallocate PDUs
+    pub fn any_index(&self) -> bool {
+        self.is_set(ANY_INDEX)
+    }
+
+    /// set ANY_INDEX
+    pub fn set_any_index(&mut self, value: bool) {
+        self.set(ANY_INDEX, value)
+    }
+
+    /// NON_DEFAULT_CONTEXT, the PDU contains a context
+    pub fn non_default_context(&self) -> bool {
+        self.is_set(NON_DEFAULT_CONTEXT)
+    }
+
+    /// set NON_DEFAULT_CONTEXT
+    pub fn set_non_default_context(&mut self, value: bool) {
+        self.set(NON_DEFAULT_CONTEXT, value)
+    }
+
+    /// NETWORK_BYTE_ORDER, multi-byte integers are encoded BigEndian
+    pub fn network_byte_order(&self) -> bool {
+        self.is_set(NETWORK_BYTE_ORDER)
+    }
+
+    /// set NETWORK_BYTE_ORDER
+    pub fn set_network_byte_order(&mut self, value: bool) {
+        self.set(NETWORK_BYTE_ORDER, value)
+    }
+
+    fn byte_order(&self) -> ByteOrder {
+        match self.network_byte_order() {
+            true => ByteOrder::BigEndian,
+            false => ByteOrder::LittleEndian,
+        }
+    }
 }
 
-const HEADER_SIZE: usize = 20;
+impl TryFrom<u8> for Flags {
+    type Error = Error;
 
-fn header_byte_order(flags: u8) -> ByteOrder {
-    match is_set(flags, 1 << NETWORK_BYTE_ORDER) {
-        true => ByteOrder::BigEndian,
-        false => ByteOrder::LittleEndian,
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        Self::from_bits(bits)
     }
 }
 
+impl From<Flags> for u8 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+/// size of the fixed size PDU Header in bytes
+pub const HEADER_SIZE: usize = 20;
+
 fn context_from_bytes(header: &Header, b: &[u8]) -> Result<Option<Context>, Error> {
     let bo = header.byte_order();
-    match is_set(header.flags, 1 << NON_DEFAULT_CONTEXT) {
+    match header.flags.non_default_context() {
         false => Ok(None),
         true => Ok(Some(Context::from_bytes(b, &bo)?)),
     }
 }
 
+fn context_size(context: &Option<Context>) -> usize {
+    context.as_ref().map_or(0, Context::byte_size)
+}
+
+fn encode_context(
+    context: &Option<Context>,
+    bo: &ByteOrder,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    match context {
+        Some(c) => c.encode(bo, buf),
+        None => Ok(()),
+    }
+}
+
+// serializes the Header followed by the payload written by `payload` and back-patches payload_length once the payload
+// is complete. `size` is the payload size calculated from the sizes of the fields, it is used to reserve capacity
+// so that the PDU is written in a single pass without reallocations. On error `buf` is truncated to its original length.
+fn encode_pdu<F>(
+    header: &Header,
+    non_default_context: bool,
+    size: usize,
+    buf: &mut Vec<u8>,
+    payload: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&ByteOrder, &mut Vec<u8>) -> Result<(), Error>,
+{
+    let start = buf.len();
+    let bo = header.byte_order();
+    buf.reserve(HEADER_SIZE + size);
+
+    let mut flags = header.flags;
+    flags.set_non_default_context(non_default_context);
+    Header {
+        flags,
+        ..header.clone()
+    }
+    .encode(buf);
+
+    let written = payload(&bo, buf).and_then(|()| payload_len(buf.len() - start - HEADER_SIZE));
+    debug_assert!(
+        written.as_ref().map_or(true, |len| *len as usize == size),
+        "payload size calculated from the fields does not match the serialized payload"
+    );
+    let len = match written {
+        Ok(len) => len,
+        Err(e) => {
+            buf.truncate(start);
+            return Err(e);
+        }
+    };
+
+    // payload_length is the last field of the Header
+    let field = start + HEADER_SIZE - size_of::<u32>();
+    buf[field..start + HEADER_SIZE].copy_from_slice(&u32_to_bytes(len, &bo));
+
+    Ok(())
+}
+
+fn payload_len(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::ValueOutOfRange {
+        field: "payload_length",
+    })
+}
+
+// the payload of a PDU, serialized after the Header by the methods generated with impl_encode_for_pdu!
+trait Payload {
+    // whether the NON_DEFAULT_CONTEXT flag is set when the PDU is serialized
+    fn non_default_context(&self) -> bool {
+        false
+    }
+
+    // payload size calculated from the sizes of the fields, fails for fields that cannot be serialized
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error>;
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error>;
+}
+
+macro_rules! impl_encode_for_pdu {
+    ($($ty:ident),*) => {
+        $(
+            impl $ty {
+                /// serialize to bytes
+                pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+                    let mut result = Vec::new();
+                    self.encode(&mut result)?;
+                    Ok(result)
+                }
+
+                /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+                pub fn encode(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+                    self.encode_with(&EncodeOptions::default(), buf)
+                }
+
+                /// serialize into `buf` using `opts`, appending to its current contents. `buf` is left untouched on
+                /// error.
+                pub fn encode_with(&self, opts: &EncodeOptions, buf: &mut Vec<u8>) -> Result<(), Error> {
+                    let size = self.payload_size(opts)?;
+                    encode_pdu(&self.header, self.non_default_context(), size, buf, |bo, buf| {
+                        self.encode_payload(opts, bo, buf)
+                    })
+                }
+
+                /// set `header.payload_length` and the NON_DEFAULT_CONTEXT flag to the values the PDU is serialized
+                /// with
+                pub fn update_header(&mut self) -> Result<(), Error> {
+                    self.update_header_with(&EncodeOptions::default())
+                }
+
+                /// set `header.payload_length` and the NON_DEFAULT_CONTEXT flag to the values the PDU is serialized
+                /// with using `opts`
+                pub fn update_header_with(&mut self, opts: &EncodeOptions) -> Result<(), Error> {
+                    let payload_length = payload_len(self.payload_size(opts)?)?;
+                    let non_default_context = self.non_default_context();
+                    self.header.payload_length = payload_length;
+                    self.header.flags.set_non_default_context(non_default_context);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+fn timeout_secs(timeout: &Duration) -> Result<u8, Error> {
+    u8::try_from(timeout.as_secs()).map_err(|_| Error::ValueOutOfRange { field: "timeout" })
+}
+
+// 100ths of a second, wrapping around after about 497 days like sysUpTime itself
+fn sys_uptime_ticks(sys_uptime: &Duration) -> u32 {
+    (sys_uptime.as_millis() / 10 % (1 << 32)) as u32
+}
+
+// the payload as announced by the Header, trailing bytes that belong to the next PDU are not part of it
+pub(crate) fn payload<'a>(header: &Header, b: &'a [u8]) -> Result<&'a [u8], Error> {
+    let b = skip(b, header.byte_size())?;
+    b.get(..header.payload_length as usize)
+        .ok_or(Error::PayloadLength {
+            offset: HEADER_SIZE,
+            expected: header.payload_length,
+            available: b.len(),
+        })
+}
+
+// b is always a tail of the payload p, so its offset within the PDU can be calculated from the lengths
+pub(crate) fn offset(p: &[u8], b: &[u8]) -> usize {
+    HEADER_SIZE + p.len() - b.len()
+}
+
 impl Header {
     /// create a default Header with a specific Type
     pub fn new(ty: Type) -> Self {
         Self {
             version: 1,
             ty,
-            flags: 0,
+            flags: Flags::new(),
             session_id: 0,
             transaction_id: 0,
             packet_id: 0,
@@ -77,8 +356,8 @@ impl Header {
         }
     }
 
-    fn byte_order(&self) -> ByteOrder {
-        header_byte_order(self.flags)
+    pub(crate) fn byte_order(&self) -> ByteOrder {
+        self.flags.byte_order()
     }
 
     /// serialize to bytes
@@ -88,34 +367,45 @@ impl Header {
         // Header is not a PDU
 
         let mut result = Vec::with_capacity(HEADER_SIZE);
-        result.extend(&[
+        self.encode(&mut result);
+        result
+    }
+
+    /// serialize into `buf`, appending to its current contents
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(&[
             self.version,
             self.ty.to_byte(),
-            self.flags,
+            self.flags.bits(),
             0, /* reserved */
         ]);
 
         let bo = self.byte_order();
-        result.extend(&u32_to_bytes(self.session_id, &bo));
-        result.extend(&u32_to_bytes(self.transaction_id, &bo));
-        result.extend(&u32_to_bytes(self.packet_id, &bo));
-        result.extend(&u32_to_bytes(self.payload_length, &bo));
-
-        result
+        buf.extend(&u32_to_bytes(self.session_id, &bo));
+        buf.extend(&u32_to_bytes(self.transaction_id, &bo));
+        buf.extend(&u32_to_bytes(self.packet_id, &bo));
+        buf.extend(&u32_to_bytes(self.payload_length, &bo));
     }
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         if b.len() < HEADER_SIZE {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, HEADER_SIZE));
         }
-        let (version, ty, flags) = (b[0], Type::from_byte(b[1])?, b[2]);
-        let bo = header_byte_order(flags);
+        let (version, ty) = (b[0], Type::from_byte(b[1]).at(1)?);
+        let flags = Flags::from_bits(b[2]).at(2)?;
+        let bo = flags.byte_order();
 
         let session_id = bytes_to_u32(&b[4..], &bo)?;
         let transaction_id = bytes_to_u32(&b[8..], &bo)?;
         let packet_id = bytes_to_u32(&b[12..], &bo)?;
         let payload_length = bytes_to_u32(&b[16..], &bo)?;
+        if payload_length % 4 != 0 {
+            return Err(Error::Padding {
+                offset: 16,
+                len: payload_length as usize,
+            });
+        }
 
         Ok(Self {
             version,
@@ -131,11 +421,6 @@ impl Header {
     fn byte_size(&self) -> usize {
         HEADER_SIZE
     }
-
-    fn set_payload_len(&mut self, len: usize) -> Result<(), Error> {
-        self.payload_length = u32::try_from(len).map_err(|_| ErrorKind::InvalidData)?;
-        Ok(())
-    }
 }
 
 /// Open PDU as defined in [Section 6.2.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.1)
@@ -168,48 +453,28 @@ impl Open {
     pub fn new(id: ID, descr: &str) -> Self {
         Open {
             id,
-            descr: OctetString(descr.to_string()),
+            descr: OctetString::from(descr),
             ..Default::default()
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        let timeout_secs =
-            u8::try_from(self.timeout.as_secs()).map_err(|_| ErrorKind::InvalidData)?;
-
-        payload.push(timeout_secs);
-        payload.extend(&[0, 0, 0]); // reserved
-        payload.extend(self.id.to_bytes(&bo));
-        payload.extend(self.descr.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
         if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, size_of::<u32>())).at(offset(p, b));
         }
         let timeout = Duration::from_secs(b[0] as u64);
-        b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        b = skip(b, 4).at(offset(p, b))?;
         //
-        let id = ID::from_bytes(b, &bo)?;
-        b = b.get(id.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let id = ID::from_bytes(b, &bo).at(offset(p, b))?;
+        b = skip(b, id.byte_size()).at(offset(p, b))?;
 
-        let descr = OctetString::from_bytes(b, &bo)?;
+        let descr = OctetString::from_bytes(b, &bo).at(offset(p, b))?;
 
         Ok(Self {
             header,
@@ -220,6 +485,25 @@ impl Open {
     }
 }
 
+impl Payload for Open {
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        timeout_secs(&self.timeout)?;
+        Ok(size_of::<u32>() + self.id.encoded_size(opts) + self.descr.byte_size())
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        buf.push(timeout_secs(&self.timeout)?);
+        buf.extend(&[0, 0, 0]); // reserved
+        self.id.encode_with(bo, opts, buf);
+        self.descr.encode(bo, buf)
+    }
+}
+
 /// Close PDU as defined in [Section 6.2.2](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.2)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct Close {
@@ -248,31 +532,37 @@ impl Close {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        self.header.payload_length = 4; /* 1 reason + 3 reserved */
-        result.extend(self.header.to_bytes());
-        result.push(self.reason.to_byte());
-        result.extend(&[0, 0, 0]); /* reserved */
-
-        Ok(result)
-    }
-
     /// serialize to bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
-        let b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let b = payload(&header, b)?;
 
-        if b.len() < size_of::<u8>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+        if b.is_empty() {
+            return Err(truncated(b, size_of::<u8>())).at(HEADER_SIZE);
         }
-        let reason = CloseReason::from_byte(b[0])?;
+        let reason = CloseReason::from_byte(b[0]).at(HEADER_SIZE)?;
 
         Ok(Self { header, reason })
     }
 }
 
+impl Payload for Close {
+    fn payload_size(&self, _: &EncodeOptions) -> Result<usize, Error> {
+        Ok(4) /* 1 reason + 3 reserved */
+    }
+
+    fn encode_payload(
+        &self,
+        _: &EncodeOptions,
+        _: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        buf.push(self.reason.to_byte());
+        buf.extend(&[0, 0, 0]); /* reserved */
+        Ok(())
+    }
+}
+
 /// Register PDU as defined in [Section 6.2.3](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.3)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, Register without a subtree does not make sense.
 pub struct Register {
@@ -309,61 +599,43 @@ impl Register {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        let timeout_secs =
-            u8::try_from(self.timeout.as_secs()).map_err(|_| ErrorKind::InvalidData)?;
-
-        payload.extend(&[
-            timeout_secs,
-            self.priority,
-            self.range_subid,
-            0, /* reserved */
-        ]);
-
-        payload.extend(self.subtree.to_bytes(&bo));
-
-        if let Some(u) = &self.upper_bound {
-            payload.extend(&u32_to_bytes(*u, &bo));
-        };
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
+    /// create a new Register PDU for a subtree or a range of subtrees
+    pub fn from_range(range: OidRange) -> Self {
+        let mut register = Self::new(range.base().clone());
+        register.range_subid = range.range_subid();
+        register.upper_bound = range.upper_bound();
+        register
+    }
 
-        Ok(result)
+    /// the registered region, fails if `upper_bound` is not present exactly when `range_subid` is not 0, or if they do
+    /// not describe a valid range
+    pub fn range(&self) -> Result<OidRange, Error> {
+        OidRange::from_parts(self.subtree.clone(), self.range_subid, self.upper_bound)
     }
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(offset(p, b))?;
         if let Some(c) = &context {
-            b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
         }
 
         if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, size_of::<u32>())).at(offset(p, b));
         }
         let (timeout, priority, range_subid) = (Duration::from_secs(b[0] as u64), b[1], b[2]);
-        b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        b = skip(b, 4).at(offset(p, b))?;
 
-        let subtree = ID::from_bytes(b, &bo)?;
-        b = b.get(subtree.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let subtree = ID::from_bytes(b, &bo).at(offset(p, b))?;
+        b = skip(b, subtree.byte_size()).at(offset(p, b))?;
 
         let upper_bound = if range_subid != 0 {
-            Some(bytes_to_u32(b, &bo)?)
+            Some(bytes_to_u32(b, &bo).at(offset(p, b))?)
         } else {
             None
         };
@@ -380,6 +652,52 @@ impl Register {
     }
 }
 
+impl Payload for Register {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        timeout_secs(&self.timeout)?;
+        check_range(self.range_subid, &self.upper_bound)?;
+        Ok(context_size(&self.context)
+            + size_of::<u32>()
+            + self.subtree.encoded_size(opts)
+            + self.upper_bound.map_or(0, |_| size_of::<u32>()))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        buf.extend(&[
+            timeout_secs(&self.timeout)?,
+            self.priority,
+            self.range_subid,
+            0, /* reserved */
+        ]);
+        self.subtree.encode_with(bo, opts, buf);
+        if let Some(u) = self.upper_bound {
+            buf.extend(&u32_to_bytes(u, bo));
+        };
+        Ok(())
+    }
+}
+
+// upper_bound is on the wire exactly when range_subid is not 0, anything else would be decoded differently
+fn check_range(range_subid: u8, upper_bound: &Option<u32>) -> Result<(), Error> {
+    if (range_subid != 0) != upper_bound.is_some() {
+        return Err(Error::InvalidRange {
+            range_subid,
+            upper_bound: *upper_bound,
+        });
+    }
+    Ok(())
+}
+
 /// Unregister PDU as defined in [Section 6.2.4](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.4)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, Unregister without a subtree does not make sense
 pub struct Unregister {
@@ -414,58 +732,43 @@ impl Unregister {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(&[
-            0, /* reserved */
-            self.priority,
-            self.range_subid,
-            0, /* reserved */
-        ]);
-
-        payload.extend(self.subtree.to_bytes(&bo));
-
-        if let Some(u) = &self.upper_bound {
-            payload.extend(&u32_to_bytes(*u, &bo));
-        };
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
+    /// create a new Unregister PDU for a subtree or a range of subtrees, registered with `priority`
+    pub fn from_range(range: OidRange, priority: u8) -> Self {
+        let mut unregister = Self::new(range.base().clone(), priority);
+        unregister.range_subid = range.range_subid();
+        unregister.upper_bound = range.upper_bound();
+        unregister
+    }
 
-        Ok(result)
+    /// the region to unregister, fails if `upper_bound` is not present exactly when `range_subid` is not 0, or if
+    /// they do not describe a valid range
+    pub fn range(&self) -> Result<OidRange, Error> {
+        OidRange::from_parts(self.subtree.clone(), self.range_subid, self.upper_bound)
     }
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(offset(p, b))?;
         if let Some(c) = &context {
-            b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
         }
 
         if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, size_of::<u32>())).at(offset(p, b));
         }
         let (priority, range_subid) = (/* b[0] reserved */ b[1], b[2]);
-        b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        b = skip(b, 4).at(offset(p, b))?;
 
-        let subtree = ID::from_bytes(b, &bo)?;
-        b = b.get(subtree.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let subtree = ID::from_bytes(b, &bo).at(offset(p, b))?;
+        b = skip(b, subtree.byte_size()).at(offset(p, b))?;
 
         let upper_bound = if range_subid != 0 {
-            Some(bytes_to_u32(b, &bo)?)
+            Some(bytes_to_u32(b, &bo).at(offset(p, b))?)
         } else {
             None
         };
@@ -481,6 +784,40 @@ impl Unregister {
     }
 }
 
+impl Payload for Unregister {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        check_range(self.range_subid, &self.upper_bound)?;
+        Ok(context_size(&self.context)
+            + size_of::<u32>()
+            + self.subtree.encoded_size(opts)
+            + self.upper_bound.map_or(0, |_| size_of::<u32>()))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        buf.extend(&[
+            0, /* reserved */
+            self.priority,
+            self.range_subid,
+            0, /* reserved */
+        ]);
+        self.subtree.encode_with(bo, opts, buf);
+        if let Some(u) = self.upper_bound {
+            buf.extend(&u32_to_bytes(u, bo));
+        };
+        Ok(())
+    }
+}
+
 /// Get PDU as defined in [Section 6.2.5](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.5)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, Get without a SearchRangeList does not make sense
 pub struct Get {
@@ -496,14 +833,15 @@ pub struct Get {
 fn get_alike_from_bytes(b: &[u8]) -> Result<(Header, Option<Context>, SearchRangeList), Error> {
     let header = Header::from_bytes(b)?;
     let bo = header.byte_order();
-    let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+    let p = payload(&header, b)?;
+    let mut b = p;
 
-    let context = context_from_bytes(&header, b)?;
+    let context = context_from_bytes(&header, b).at(offset(p, b))?;
     if let Some(c) = &context {
-        b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        b = skip(b, c.byte_size()).at(offset(p, b))?;
     }
 
-    let sr = SearchRangeList::from_bytes(b, &bo)?;
+    let sr = SearchRangeList::from_bytes(b, &bo).at(offset(p, b))?;
 
     Ok((header, context, sr))
 }
@@ -520,25 +858,6 @@ impl Get {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.sr.to_bytes(&bo));
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, sr) = get_alike_from_bytes(b)?;
@@ -551,6 +870,27 @@ impl Get {
     }
 }
 
+impl Payload for Get {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.sr.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.sr.encode_with(bo, opts, buf);
+        Ok(())
+    }
+}
+
 // exact copy of Get (except header type)
 /// GetNext PDU as defined in [Section 6.2.6](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.6)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, GetNext without a SearchRangeList does not make sense
@@ -575,25 +915,6 @@ impl GetNext {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.sr.to_bytes(&bo));
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, sr) = get_alike_from_bytes(b)?;
@@ -606,6 +927,27 @@ impl GetNext {
     }
 }
 
+impl Payload for GetNext {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.sr.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.sr.encode_with(bo, opts, buf);
+        Ok(())
+    }
+}
+
 /// GetBulk PDU as defined in [Section 6.2.7](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.7)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, GetBulk without a SearchRangeList does not make sense
 pub struct GetBulk {
@@ -635,45 +977,25 @@ impl GetBulk {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(&u16_to_bytes(self.non_repeaters, &bo));
-        payload.extend(&u16_to_bytes(self.max_repetitions, &bo));
-        payload.extend(self.sr.to_bytes(&bo));
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(offset(p, b))?;
         if let Some(c) = &context {
-            b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
         }
 
         if b.len() < size_of::<u32>() {
-            return Err(Error::from(ErrorKind::InvalidData));
+            return Err(truncated(b, size_of::<u32>())).at(offset(p, b));
         }
-        let (non_repeaters, max_repetitions) = (bytes_to_u16(b, &bo)?, bytes_to_u16(&b[2..], &bo)?);
-        b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        let (non_repeaters, max_repetitions) = (bytes_to_u16(b, &bo)?, bytes_to_u16(&b[2..], &bo)?); // size already checked
+        b = skip(b, 4).at(offset(p, b))?;
 
-        let sr = SearchRangeList::from_bytes(b, &bo)?;
+        let sr = SearchRangeList::from_bytes(b, &bo).at(offset(p, b))?;
 
         Ok(Self {
             header,
@@ -685,6 +1007,29 @@ impl GetBulk {
     }
 }
 
+impl Payload for GetBulk {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + size_of::<u32>() + self.sr.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        buf.extend(&u16_to_bytes(self.non_repeaters, bo));
+        buf.extend(&u16_to_bytes(self.max_repetitions, bo));
+        self.sr.encode_with(bo, opts, buf);
+        Ok(())
+    }
+}
+
 /// TestSet PDU as defined in [Section 6.2.8](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.8)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, a TestSet without a VarBindList does not make sense
 pub struct TestSet {
@@ -699,14 +1044,15 @@ pub struct TestSet {
 fn testset_alike_from_bytes(b: &[u8]) -> Result<(Header, Option<Context>, VarBindList), Error> {
     let header = Header::from_bytes(b)?;
     let bo = header.byte_order();
-    let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+    let p = payload(&header, b)?;
+    let mut b = p;
 
-    let context = context_from_bytes(&header, b)?;
+    let context = context_from_bytes(&header, b).at(offset(p, b))?;
     if let Some(c) = &context {
-        b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        b = skip(b, c.byte_size()).at(offset(p, b))?;
     }
 
-    let vb = VarBindList::from_bytes(b, &bo)?;
+    let vb = VarBindList::from_bytes(b, &bo).at(offset(p, b))?;
 
     Ok((header, context, vb))
 }
@@ -723,25 +1069,6 @@ impl TestSet {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.vb.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, vb) = testset_alike_from_bytes(b)?;
@@ -753,6 +1080,26 @@ impl TestSet {
     }
 }
 
+impl Payload for TestSet {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.vb.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.vb.encode_with(bo, opts, buf)
+    }
+}
+
 // same as TestSet
 /// Notify PDU as defined in [Section 6.2.10](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.10)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, a Notify without a VarBindList does not make sense
@@ -777,25 +1124,6 @@ impl Notify {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.vb.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, vb) = testset_alike_from_bytes(b)?;
@@ -807,6 +1135,26 @@ impl Notify {
     }
 }
 
+impl Payload for Notify {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.vb.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.vb.encode_with(bo, opts, buf)
+    }
+}
+
 // same as TestSet
 /// IndexAllocate PDU as defined in [Section 6.2.12](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.12)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, IndexAllocate without a VarBindList does not make sense
@@ -831,25 +1179,6 @@ impl IndexAllocate {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.vb.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, vb) = testset_alike_from_bytes(b)?;
@@ -861,6 +1190,26 @@ impl IndexAllocate {
     }
 }
 
+impl Payload for IndexAllocate {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.vb.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.vb.encode_with(bo, opts, buf)
+    }
+}
+
 // same as TestSet
 /// IndexDeallocate PDU as defined in [Section 6.2.13](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.13)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, IndexDeallocate without a VarBindList does not make sense
@@ -885,25 +1234,6 @@ impl IndexDeallocate {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.vb.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let (header, context, vb) = testset_alike_from_bytes(b)?;
@@ -915,6 +1245,26 @@ impl IndexDeallocate {
     }
 }
 
+impl Payload for IndexDeallocate {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.vb.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.vb.encode_with(bo, opts, buf)
+    }
+}
+
 /// CommitSet PDU as defined in [Section 6.2.9](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.9)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct CommitSet {
@@ -936,11 +1286,6 @@ impl CommitSet {
         Self::default()
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        Ok(self.header.to_bytes())
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         Ok(Self {
@@ -949,6 +1294,21 @@ impl CommitSet {
     }
 }
 
+impl Payload for CommitSet {
+    fn payload_size(&self, _: &EncodeOptions) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn encode_payload(
+        &self,
+        _: &EncodeOptions,
+        _: &ByteOrder,
+        _: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 // same as CommitSet
 /// UndoSet PDU as defined in [Section 6.2.9](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.9)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -971,11 +1331,6 @@ impl UndoSet {
         Self::default()
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        Ok(self.header.to_bytes())
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         Ok(Self {
@@ -984,6 +1339,21 @@ impl UndoSet {
     }
 }
 
+impl Payload for UndoSet {
+    fn payload_size(&self, _: &EncodeOptions) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn encode_payload(
+        &self,
+        _: &EncodeOptions,
+        _: &ByteOrder,
+        _: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 // same as CommitSet
 /// CleanupSet PDU as defined in [Section 6.2.9](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.9)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -1006,11 +1376,6 @@ impl CleanupSet {
         Self::default()
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        Ok(self.header.to_bytes())
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         Ok(Self {
@@ -1019,6 +1384,21 @@ impl CleanupSet {
     }
 }
 
+impl Payload for CleanupSet {
+    fn payload_size(&self, _: &EncodeOptions) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn encode_payload(
+        &self,
+        _: &EncodeOptions,
+        _: &ByteOrder,
+        _: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 /// Ping PDU as defined in [Section 6.2.11](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.11)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct Ping {
@@ -1043,34 +1423,36 @@ impl Ping {
         Self::default()
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
-        let b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let b = payload(&header, b)?;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(HEADER_SIZE)?;
 
         Ok(Self { header, context })
     }
 }
 
+impl Payload for Ping {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, _: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context))
+    }
+
+    fn encode_payload(
+        &self,
+        _: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)
+    }
+}
+
 /// AddAgentCaps PDU as defined in [Section 6.2.14](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.14)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, AddAgentCaps without ID does not make sense
 pub struct AddAgentCaps {
@@ -1088,7 +1470,7 @@ impl AddAgentCaps {
     /// create a new AddAgentCaps PDU from an ID and a description
     pub fn new(id: ID, descr: &str) -> Self {
         let header = Header::new(Type::AddAgentCaps);
-        let descr = OctetString(descr.to_string());
+        let descr = OctetString::from(descr);
 
         Self {
             header,
@@ -1098,41 +1480,22 @@ impl AddAgentCaps {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.id.to_bytes(&bo));
-        payload.extend(self.descr.to_bytes(&bo)?);
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(offset(p, b))?;
         if let Some(c) = &context {
-            b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
         }
 
-        let id = ID::from_bytes(b, &bo)?;
-        b = b.get(id.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let id = ID::from_bytes(b, &bo).at(offset(p, b))?;
+        b = skip(b, id.byte_size()).at(offset(p, b))?;
 
-        let descr = OctetString::from_bytes(b, &bo)?;
+        let descr = OctetString::from_bytes(b, &bo).at(offset(p, b))?;
 
         Ok(Self {
             header,
@@ -1143,6 +1506,27 @@ impl AddAgentCaps {
     }
 }
 
+impl Payload for AddAgentCaps {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.id.encoded_size(opts) + self.descr.byte_size())
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.id.encode_with(bo, opts, buf);
+        self.descr.encode(bo, buf)
+    }
+}
+
 /// RemoveAgentCaps PDU as defined in [Section 6.2.15](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.15)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)] // no Default, RemoveAgentCaps without ID does not make sense
 pub struct RemoveAgentCaps {
@@ -1166,37 +1550,19 @@ impl RemoveAgentCaps {
         }
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        if let Some(c) = &self.context {
-            payload.extend(c.0.to_bytes(&bo)?);
-        };
-
-        payload.extend(self.id.to_bytes(&bo));
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let context = context_from_bytes(&header, b)?;
+        let context = context_from_bytes(&header, b).at(offset(p, b))?;
         if let Some(c) = &context {
-            b = b.get(c.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+            b = skip(b, c.byte_size()).at(offset(p, b))?;
         }
 
-        let id = ID::from_bytes(b, &bo)?;
+        let id = ID::from_bytes(b, &bo).at(offset(p, b))?;
 
         Ok(Self {
             header,
@@ -1206,6 +1572,27 @@ impl RemoveAgentCaps {
     }
 }
 
+impl Payload for RemoveAgentCaps {
+    fn non_default_context(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(context_size(&self.context) + self.id.encoded_size(opts))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        encode_context(&self.context, bo, buf)?;
+        self.id.encode_with(bo, opts, buf);
+        Ok(())
+    }
+}
+
 // for "administrative" PDU types
 /// Response PDU as defined in [Section 6.2.16](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2.16)
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -1265,54 +1652,28 @@ impl Response {
         response
     }
 
-    /// serialize to bytes
-    pub fn to_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        let mut payload = Vec::new();
-        let bo = self.header.byte_order();
-
-        // 100ths of a second...
-        let sys_uptime =
-            u32::try_from(self.sys_uptime.as_millis() / 10).map_err(|_| ErrorKind::InvalidData)?;
-        let sys_uptime = u32_to_bytes(sys_uptime, &bo);
-        payload.extend(&sys_uptime);
-
-        payload.extend(&self.res_error.to_bytes(&bo));
-
-        payload.extend(&u16_to_bytes(self.res_index, &bo));
-
-        if let Some(vb) = &self.vb {
-            payload.extend(vb.to_bytes(&bo)?);
-        }
-
-        self.header.set_payload_len(payload.len())?;
-        result.extend(self.header.to_bytes());
-        result.extend(payload);
-
-        Ok(result)
-    }
-
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
         let header = Header::from_bytes(b)?;
         let bo = header.byte_order();
-        let mut b = b.get(header.byte_size()..).ok_or(ErrorKind::InvalidData)?;
+        let p = payload(&header, b)?;
+        let mut b = p;
 
-        let sys_uptime = bytes_to_u32(b, &bo)?;
+        let sys_uptime = bytes_to_u32(b, &bo).at(offset(p, b))?;
         // 100th of a second...
-        let sys_uptime = Duration::from_millis((sys_uptime * 10) as u64);
-        b = b.get(4..).ok_or(ErrorKind::InvalidData)?;
+        let sys_uptime = Duration::from_millis(sys_uptime as u64 * 10);
+        b = skip(b, 4).at(offset(p, b))?;
 
-        let res_error = ResError::from_bytes(b, &bo)?;
-        b = b
-            .get(res_error.byte_size()..)
-            .ok_or(ErrorKind::InvalidData)?;
+        let res_error = ResError::from_bytes(b, &bo).at(offset(p, b))?;
+        b = skip(b, res_error.byte_size()).at(offset(p, b))?;
 
-        let res_index = bytes_to_u16(b, &bo)?;
+        let res_index = bytes_to_u16(b, &bo).at(offset(p, b))?;
+        b = skip(b, size_of::<u16>()).at(offset(p, b))?;
 
-        let vb = match b.get(2..) {
-            None => None,
-            Some(b) => Some(VarBindList::from_bytes(b, &bo)?),
+        // an empty payload after res_index is a Response without a VarBindList
+        let vb = match b {
+            [] => None,
+            b => Some(VarBindList::from_bytes(b, &bo).at(offset(p, b))?),
         };
 
         Ok(Self {
@@ -1325,6 +1686,339 @@ impl Response {
     }
 }
 
+impl Payload for Response {
+    fn payload_size(&self, opts: &EncodeOptions) -> Result<usize, Error> {
+        Ok(size_of::<u32>()
+            + self.res_error.byte_size()
+            + size_of::<u16>()
+            + self.vb.as_ref().map_or(0, |vb| vb.encoded_size(opts)))
+    }
+
+    fn encode_payload(
+        &self,
+        opts: &EncodeOptions,
+        bo: &ByteOrder,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        buf.extend(&u32_to_bytes(sys_uptime_ticks(&self.sys_uptime), bo));
+        buf.extend(&self.res_error.to_bytes(bo));
+        buf.extend(&u16_to_bytes(self.res_index, bo));
+        if let Some(vb) = &self.vb {
+            vb.encode_with(bo, opts, buf)?;
+        }
+        Ok(())
+    }
+}
+
+/// Any of the PDUs defined in [Section 6.2](https://datatracker.ietf.org/doc/html/rfc2741#section-6.2)
+///
+/// The concrete PDU is selected by the type found in the Header, which makes this the natural entry point for a
+/// receive loop.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use agentx::pdu::{Pdu, Response};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let bytes = vec![]; // assume we received some actual bytes from a master agent
+/// match Pdu::from_bytes(&bytes)? {
+///     Pdu::Get(get) => { /* answer the Get */ }
+///     Pdu::GetNext(get_next) => { /* answer the GetNext */ }
+///     pdu => {
+///         let mut response = Response::from_header(pdu.header());
+///         // ...
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Pdu {
+    /// Open PDU
+    Open(Open),
+    /// Close PDU
+    Close(Close),
+    /// Register PDU
+    Register(Register),
+    /// Unregister PDU
+    Unregister(Unregister),
+    /// Get PDU
+    Get(Get),
+    /// GetNext PDU
+    GetNext(GetNext),
+    /// GetBulk PDU
+    GetBulk(GetBulk),
+    /// TestSet PDU
+    TestSet(TestSet),
+    /// CommitSet PDU
+    CommitSet(CommitSet),
+    /// UndoSet PDU
+    UndoSet(UndoSet),
+    /// CleanupSet PDU
+    CleanupSet(CleanupSet),
+    /// Notify PDU
+    Notify(Notify),
+    /// Ping PDU
+    Ping(Ping),
+    /// IndexAllocate PDU
+    IndexAllocate(IndexAllocate),
+    /// IndexDeallocate PDU
+    IndexDeallocate(IndexDeallocate),
+    /// AddAgentCaps PDU
+    AddAgentCaps(AddAgentCaps),
+    /// RemoveAgentCaps PDU
+    RemoveAgentCaps(RemoveAgentCaps),
+    /// Response PDU
+    Response(Response),
+}
+
+impl Pdu {
+    /// common Header of the PDU
+    pub fn header(&self) -> &Header {
+        match self {
+            Self::Open(p) => &p.header,
+            Self::Close(p) => &p.header,
+            Self::Register(p) => &p.header,
+            Self::Unregister(p) => &p.header,
+            Self::Get(p) => &p.header,
+            Self::GetNext(p) => &p.header,
+            Self::GetBulk(p) => &p.header,
+            Self::TestSet(p) => &p.header,
+            Self::CommitSet(p) => &p.header,
+            Self::UndoSet(p) => &p.header,
+            Self::CleanupSet(p) => &p.header,
+            Self::Notify(p) => &p.header,
+            Self::Ping(p) => &p.header,
+            Self::IndexAllocate(p) => &p.header,
+            Self::IndexDeallocate(p) => &p.header,
+            Self::AddAgentCaps(p) => &p.header,
+            Self::RemoveAgentCaps(p) => &p.header,
+            Self::Response(p) => &p.header,
+        }
+    }
+
+    /// mutable reference to the common Header of the PDU, e.g., to set `session_id` or `packet_id`
+    pub fn header_mut(&mut self) -> &mut Header {
+        match self {
+            Self::Open(p) => &mut p.header,
+            Self::Close(p) => &mut p.header,
+            Self::Register(p) => &mut p.header,
+            Self::Unregister(p) => &mut p.header,
+            Self::Get(p) => &mut p.header,
+            Self::GetNext(p) => &mut p.header,
+            Self::GetBulk(p) => &mut p.header,
+            Self::TestSet(p) => &mut p.header,
+            Self::CommitSet(p) => &mut p.header,
+            Self::UndoSet(p) => &mut p.header,
+            Self::CleanupSet(p) => &mut p.header,
+            Self::Notify(p) => &mut p.header,
+            Self::Ping(p) => &mut p.header,
+            Self::IndexAllocate(p) => &mut p.header,
+            Self::IndexDeallocate(p) => &mut p.header,
+            Self::AddAgentCaps(p) => &mut p.header,
+            Self::RemoveAgentCaps(p) => &mut p.header,
+            Self::Response(p) => &mut p.header,
+        }
+    }
+
+    /// PDU type as found in the Header
+    pub fn ty(&self) -> Type {
+        self.header().ty.clone()
+    }
+
+    /// serialize to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Open(p) => p.to_bytes(),
+            Self::Close(p) => p.to_bytes(),
+            Self::Register(p) => p.to_bytes(),
+            Self::Unregister(p) => p.to_bytes(),
+            Self::Get(p) => p.to_bytes(),
+            Self::GetNext(p) => p.to_bytes(),
+            Self::GetBulk(p) => p.to_bytes(),
+            Self::TestSet(p) => p.to_bytes(),
+            Self::CommitSet(p) => p.to_bytes(),
+            Self::UndoSet(p) => p.to_bytes(),
+            Self::CleanupSet(p) => p.to_bytes(),
+            Self::Notify(p) => p.to_bytes(),
+            Self::Ping(p) => p.to_bytes(),
+            Self::IndexAllocate(p) => p.to_bytes(),
+            Self::IndexDeallocate(p) => p.to_bytes(),
+            Self::AddAgentCaps(p) => p.to_bytes(),
+            Self::RemoveAgentCaps(p) => p.to_bytes(),
+            Self::Response(p) => p.to_bytes(),
+        }
+    }
+
+    /// serialize into `buf`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            Self::Open(p) => p.encode(buf),
+            Self::Close(p) => p.encode(buf),
+            Self::Register(p) => p.encode(buf),
+            Self::Unregister(p) => p.encode(buf),
+            Self::Get(p) => p.encode(buf),
+            Self::GetNext(p) => p.encode(buf),
+            Self::GetBulk(p) => p.encode(buf),
+            Self::TestSet(p) => p.encode(buf),
+            Self::CommitSet(p) => p.encode(buf),
+            Self::UndoSet(p) => p.encode(buf),
+            Self::CleanupSet(p) => p.encode(buf),
+            Self::Notify(p) => p.encode(buf),
+            Self::Ping(p) => p.encode(buf),
+            Self::IndexAllocate(p) => p.encode(buf),
+            Self::IndexDeallocate(p) => p.encode(buf),
+            Self::AddAgentCaps(p) => p.encode(buf),
+            Self::RemoveAgentCaps(p) => p.encode(buf),
+            Self::Response(p) => p.encode(buf),
+        }
+    }
+
+    /// serialize into `buf` using `opts`, appending to its current contents. `buf` is left untouched on error.
+    pub fn encode_with(&self, opts: &EncodeOptions, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            Self::Open(p) => p.encode_with(opts, buf),
+            Self::Close(p) => p.encode_with(opts, buf),
+            Self::Register(p) => p.encode_with(opts, buf),
+            Self::Unregister(p) => p.encode_with(opts, buf),
+            Self::Get(p) => p.encode_with(opts, buf),
+            Self::GetNext(p) => p.encode_with(opts, buf),
+            Self::GetBulk(p) => p.encode_with(opts, buf),
+            Self::TestSet(p) => p.encode_with(opts, buf),
+            Self::CommitSet(p) => p.encode_with(opts, buf),
+            Self::UndoSet(p) => p.encode_with(opts, buf),
+            Self::CleanupSet(p) => p.encode_with(opts, buf),
+            Self::Notify(p) => p.encode_with(opts, buf),
+            Self::Ping(p) => p.encode_with(opts, buf),
+            Self::IndexAllocate(p) => p.encode_with(opts, buf),
+            Self::IndexDeallocate(p) => p.encode_with(opts, buf),
+            Self::AddAgentCaps(p) => p.encode_with(opts, buf),
+            Self::RemoveAgentCaps(p) => p.encode_with(opts, buf),
+            Self::Response(p) => p.encode_with(opts, buf),
+        }
+    }
+
+    /// set `header.payload_length` and the NON_DEFAULT_CONTEXT flag to the values the PDU is serialized with
+    pub fn update_header(&mut self) -> Result<(), Error> {
+        self.update_header_with(&EncodeOptions::default())
+    }
+
+    /// set `header.payload_length` and the NON_DEFAULT_CONTEXT flag to the values the PDU is serialized with
+    /// using `opts`
+    pub fn update_header_with(&mut self, opts: &EncodeOptions) -> Result<(), Error> {
+        match self {
+            Self::Open(p) => p.update_header_with(opts),
+            Self::Close(p) => p.update_header_with(opts),
+            Self::Register(p) => p.update_header_with(opts),
+            Self::Unregister(p) => p.update_header_with(opts),
+            Self::Get(p) => p.update_header_with(opts),
+            Self::GetNext(p) => p.update_header_with(opts),
+            Self::GetBulk(p) => p.update_header_with(opts),
+            Self::TestSet(p) => p.update_header_with(opts),
+            Self::CommitSet(p) => p.update_header_with(opts),
+            Self::UndoSet(p) => p.update_header_with(opts),
+            Self::CleanupSet(p) => p.update_header_with(opts),
+            Self::Notify(p) => p.update_header_with(opts),
+            Self::Ping(p) => p.update_header_with(opts),
+            Self::IndexAllocate(p) => p.update_header_with(opts),
+            Self::IndexDeallocate(p) => p.update_header_with(opts),
+            Self::AddAgentCaps(p) => p.update_header_with(opts),
+            Self::RemoveAgentCaps(p) => p.update_header_with(opts),
+            Self::Response(p) => p.update_header_with(opts),
+        }
+    }
+
+    /// serialize into `buf` and write the PDU to `w`. `buf` is cleared first, it only serves as scratch space that can
+    /// be reused for the next PDU.
+    pub fn write_to<W: Write>(&self, w: &mut W, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.clear();
+        self.encode(buf)?;
+        w.write_all(buf)
+    }
+
+    /// deserialize from bytes, the concrete PDU is selected by the type in the Header
+    pub fn from_bytes(b: &[u8]) -> Result<Self, Error> {
+        let header = Header::from_bytes(b)?;
+
+        let pdu = match header.ty {
+            Type::Open => Self::Open(Open::from_bytes(b)?),
+            Type::Close => Self::Close(Close::from_bytes(b)?),
+            Type::Register => Self::Register(Register::from_bytes(b)?),
+            Type::Unregister => Self::Unregister(Unregister::from_bytes(b)?),
+            Type::Get => Self::Get(Get::from_bytes(b)?),
+            Type::GetNext => Self::GetNext(GetNext::from_bytes(b)?),
+            Type::GetBulk => Self::GetBulk(GetBulk::from_bytes(b)?),
+            Type::TestSet => Self::TestSet(TestSet::from_bytes(b)?),
+            Type::CommitSet => Self::CommitSet(CommitSet::from_bytes(b)?),
+            Type::UndoSet => Self::UndoSet(UndoSet::from_bytes(b)?),
+            Type::CleanupSet => Self::CleanupSet(CleanupSet::from_bytes(b)?),
+            Type::Notify => Self::Notify(Notify::from_bytes(b)?),
+            Type::Ping => Self::Ping(Ping::from_bytes(b)?),
+            Type::IndexAllocate => Self::IndexAllocate(IndexAllocate::from_bytes(b)?),
+            Type::IndexDeallocate => Self::IndexDeallocate(IndexDeallocate::from_bytes(b)?),
+            Type::AddAgentCaps => Self::AddAgentCaps(AddAgentCaps::from_bytes(b)?),
+            Type::RemoveAgentCaps => Self::RemoveAgentCaps(RemoveAgentCaps::from_bytes(b)?),
+            Type::Response => Self::Response(Response::from_bytes(b)?),
+        };
+
+        Ok(pdu)
+    }
+}
+
+macro_rules! impl_from_for_pdu {
+    ($($ty:ident),*) => {
+        $(
+            impl From<$ty> for Pdu {
+                fn from(pdu: $ty) -> Self {
+                    Self::$ty(pdu)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_for_pdu!(
+    Open,
+    Close,
+    Register,
+    Unregister,
+    Get,
+    GetNext,
+    GetBulk,
+    TestSet,
+    CommitSet,
+    UndoSet,
+    CleanupSet,
+    Notify,
+    Ping,
+    IndexAllocate,
+    IndexDeallocate,
+    AddAgentCaps,
+    RemoveAgentCaps,
+    Response
+);
+
+impl_encode_for_pdu!(
+    Open,
+    Close,
+    Register,
+    Unregister,
+    Get,
+    GetNext,
+    GetBulk,
+    TestSet,
+    CommitSet,
+    UndoSet,
+    CleanupSet,
+    Notify,
+    Ping,
+    IndexAllocate,
+    IndexDeallocate,
+    AddAgentCaps,
+    RemoveAgentCaps,
+    Response
+);
+
 /// PDU types
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Type {
@@ -1412,7 +2106,7 @@ impl Type {
             16 => Self::AddAgentCaps,
             17 => Self::RemoveAgentCaps,
             18 => Self::Response,
-            _ => return Err(Error::from(ErrorKind::InvalidData)),
+            _ => return Err(Error::UnknownPduType { offset: 0, ty: b }),
         };
 
         Ok(ty)
@@ -1458,7 +2152,12 @@ impl CloseReason {
             4 => Self::Timeouts,
             5 => Self::Shutdown,
             6 => Self::ByManager,
-            _ => return Err(Error::from(ErrorKind::InvalidData)),
+            _ => {
+                return Err(Error::InvalidCloseReason {
+                    offset: 0,
+                    reason: b,
+                })
+            }
         };
 
         Ok(ty)
@@ -1467,10 +2166,48 @@ impl CloseReason {
 
 /// Indicates error status.  Within responses to the set of "administrative" PDU types listed in [Section 6.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1), "AgentX PDU Header", values are limited to the following.
 /// Within responses to the set of "SNMP request processing" PDU types listed in [Section 6.1](https://datatracker.ietf.org/doc/html/rfc2741#section-6.1) "AgentX PDU Header", values may also include those defined for errors in the SNMPv2 PDU.
+///
+/// The SNMPv2 error-status values are defined in [RFC 3416 Section 3](https://datatracker.ietf.org/doc/html/rfc3416#section-3).
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum ResError {
     /// NoAgentXError
     NoAgentXError,
+    /// SNMPv2 tooBig
+    TooBig,
+    /// SNMPv2 noSuchName
+    NoSuchName,
+    /// SNMPv2 badValue
+    BadValue,
+    /// SNMPv2 readOnly
+    ReadOnly,
+    /// SNMPv2 genErr
+    GenErr,
+    /// SNMPv2 noAccess
+    NoAccess,
+    /// SNMPv2 wrongType
+    WrongType,
+    /// SNMPv2 wrongLength
+    WrongLength,
+    /// SNMPv2 wrongEncoding
+    WrongEncoding,
+    /// SNMPv2 wrongValue
+    WrongValue,
+    /// SNMPv2 noCreation
+    NoCreation,
+    /// SNMPv2 inconsistentValue
+    InconsistentValue,
+    /// SNMPv2 resourceUnavailable
+    ResourceUnavailable,
+    /// SNMPv2 commitFailed
+    CommitFailed,
+    /// SNMPv2 undoFailed
+    UndoFailed,
+    /// SNMPv2 authorizationError
+    AuthorizationError,
+    /// SNMPv2 notWritable
+    NotWritable,
+    /// SNMPv2 inconsistentName
+    InconsistentName,
     /// OpenFailed
     OpenFailed,
     /// NotOpen
@@ -1504,6 +2241,24 @@ impl ResError {
     pub fn to_bytes(&self, bo: &ByteOrder) -> [u8; 2] {
         let val = match self {
             Self::NoAgentXError => 0,
+            Self::TooBig => 1,
+            Self::NoSuchName => 2,
+            Self::BadValue => 3,
+            Self::ReadOnly => 4,
+            Self::GenErr => 5,
+            Self::NoAccess => 6,
+            Self::WrongType => 7,
+            Self::WrongLength => 8,
+            Self::WrongEncoding => 9,
+            Self::WrongValue => 10,
+            Self::NoCreation => 11,
+            Self::InconsistentValue => 12,
+            Self::ResourceUnavailable => 13,
+            Self::CommitFailed => 14,
+            Self::UndoFailed => 15,
+            Self::AuthorizationError => 16,
+            Self::NotWritable => 17,
+            Self::InconsistentName => 18,
             Self::OpenFailed => 256,
             Self::NotOpen => 257,
             Self::IndexWrongType => 258,
@@ -1528,8 +2283,27 @@ impl ResError {
 
     /// deserialize from bytes
     pub fn from_bytes(b: &[u8], bo: &ByteOrder) -> Result<Self, Error> {
-        let re = match bytes_to_u16(b, bo)? {
+        let code = bytes_to_u16(b, bo)?;
+        let re = match code {
             0 => Self::NoAgentXError,
+            1 => Self::TooBig,
+            2 => Self::NoSuchName,
+            3 => Self::BadValue,
+            4 => Self::ReadOnly,
+            5 => Self::GenErr,
+            6 => Self::NoAccess,
+            7 => Self::WrongType,
+            8 => Self::WrongLength,
+            9 => Self::WrongEncoding,
+            10 => Self::WrongValue,
+            11 => Self::NoCreation,
+            12 => Self::InconsistentValue,
+            13 => Self::ResourceUnavailable,
+            14 => Self::CommitFailed,
+            15 => Self::UndoFailed,
+            16 => Self::AuthorizationError,
+            17 => Self::NotWritable,
+            18 => Self::InconsistentName,
             256 => Self::OpenFailed,
             257 => Self::NotOpen,
             258 => Self::IndexWrongType,
@@ -1543,7 +2317,7 @@ impl ResError {
             266 => Self::ParseError,
             267 => Self::RequestDenied,
             268 => Self::ProcessingError,
-            _ => return Err(Error::from(ErrorKind::InvalidData)),
+            _ => return Err(Error::InvalidResError { offset: 0, code }),
         };
 
         Ok(re)
@@ -1553,6 +2327,7 @@ impl ResError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::encodings::{SearchRange, Value, VarBind};
     use std::str::FromStr;
 
     #[test]
@@ -1588,6 +2363,136 @@ mod tests {
         assert_eq!(expected, open.to_bytes().unwrap());
     }
 
+    #[test]
+    fn header_flags() {
+        let mut flags = Flags::new();
+        flags.set_instance_registration(true);
+        flags.set_any_index(true);
+        flags.set_network_byte_order(true);
+        assert_eq!(flags.bits(), 0b0001_0101);
+        assert!(!flags.new_index());
+        flags.set_any_index(false);
+        assert_eq!(u8::from(flags), 0b0001_0001);
+        assert_eq!(Flags::try_from(0b0001_0001), Ok(flags));
+
+        let mut bytes = Ping::new().to_bytes().unwrap();
+        bytes[2] = 0x20;
+        assert_eq!(
+            Header::from_bytes(&bytes),
+            Err(Error::ReservedFlags {
+                offset: 2,
+                flags: 0x20
+            })
+        );
+
+        // NON_DEFAULT_CONTEXT follows the context
+        let mut ping = Ping::new();
+        ping.header.flags.set_non_default_context(true);
+        let bytes = ping.to_bytes().unwrap();
+        assert_eq!(bytes[2], 0);
+        ping.context = Some(Context(OctetString::from("rck")));
+        let bytes = ping.to_bytes().unwrap();
+        assert_eq!(bytes[2], 1 << NON_DEFAULT_CONTEXT);
+        ping.update_header().unwrap();
+        assert_eq!(Ping::from_bytes(&bytes).unwrap(), ping);
+    }
+
+    fn with_context(mut pdu: Pdu, context: Option<Context>) -> Pdu {
+        match &mut pdu {
+            Pdu::Register(p) => p.context = context,
+            Pdu::Unregister(p) => p.context = context,
+            Pdu::Get(p) => p.context = context,
+            Pdu::GetNext(p) => p.context = context,
+            Pdu::GetBulk(p) => p.context = context,
+            Pdu::TestSet(p) => p.context = context,
+            Pdu::Notify(p) => p.context = context,
+            Pdu::Ping(p) => p.context = context,
+            Pdu::IndexAllocate(p) => p.context = context,
+            Pdu::IndexDeallocate(p) => p.context = context,
+            Pdu::AddAgentCaps(p) => p.context = context,
+            Pdu::RemoveAgentCaps(p) => p.context = context,
+            pdu => panic!("{:?} has no context", pdu.ty()),
+        }
+        pdu
+    }
+
+    #[test]
+    fn context_serde() {
+        let id = ID::from_str("1.2.3").unwrap();
+        let sr = SearchRangeList(vec![SearchRange::new(
+            ID::from_str("1.2.3.4").unwrap(),
+            ID::default(),
+        )]);
+        let vb = VarBindList(vec![VarBind::new(
+            ID::from_str("1.2.3.4").unwrap(),
+            Value::Integer(42),
+        )]);
+        let pdus: Vec<Pdu> = vec![
+            Register::new(id.clone()).into(),
+            Unregister::new(id.clone(), 127).into(),
+            Get::new(sr.clone()).into(),
+            GetNext::new(sr.clone()).into(),
+            GetBulk::new(sr).into(),
+            TestSet::new(vb.clone()).into(),
+            Notify::new(vb.clone()).into(),
+            Ping::new().into(),
+            IndexAllocate::new(vb.clone()).into(),
+            IndexDeallocate::new(vb).into(),
+            AddAgentCaps::new(id.clone(), "rck").into(),
+            RemoveAgentCaps::new(id).into(),
+        ];
+
+        for network_byte_order in vec![false, true] {
+            for context in vec![None, Some(Context(OctetString::from("ctx")))] {
+                for pdu in &pdus {
+                    let mut expected = with_context(pdu.clone(), context.clone());
+                    expected
+                        .header_mut()
+                        .flags
+                        .set_network_byte_order(network_byte_order);
+                    // a stale flag must not survive serialization
+                    expected
+                        .header_mut()
+                        .flags
+                        .set_non_default_context(context.is_none());
+
+                    let bytes = expected.to_bytes().unwrap();
+                    assert_eq!(
+                        bytes[2] & (1 << NON_DEFAULT_CONTEXT) != 0,
+                        context.is_some()
+                    );
+                    expected.update_header().unwrap();
+                    assert_eq!(
+                        expected.header().flags.non_default_context(),
+                        context.is_some()
+                    );
+                    let got = Pdu::from_bytes(&bytes).unwrap();
+                    assert_eq!(got, expected, "{:?}", expected.ty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_context_flag() {
+        let pdus: Vec<Pdu> = vec![
+            Open::new(ID::from_str("1.2.3").unwrap(), "rck").into(),
+            Close::new(CloseReason::Shutdown).into(),
+            CommitSet::new().into(),
+            UndoSet::new().into(),
+            CleanupSet::new().into(),
+            Response::new().into(),
+        ];
+
+        for mut pdu in pdus {
+            pdu.header_mut().flags.set_non_default_context(true);
+            let bytes = pdu.to_bytes().unwrap();
+            assert_eq!(bytes[2], 0, "{:?}", pdu.ty());
+            pdu.update_header().unwrap();
+            assert_eq!(Pdu::from_bytes(&bytes).unwrap(), pdu);
+        }
+    }
+
     #[test]
     fn open_serde() {
         let mut expected = Open::new(ID::from_str("1.2.3.4").unwrap(), "rck");
@@ -1595,12 +2500,14 @@ mod tests {
         expected.header.transaction_id = 2342;
         expected.header.packet_id = 3;
 
-        for bo in vec![0, NETWORK_BYTE_ORDER] {
-            if bo > 0 {
-                expected.header.flags |= 1 << bo;
-            }
+        for network_byte_order in vec![false, true] {
+            expected
+                .header
+                .flags
+                .set_network_byte_order(network_byte_order);
             let bytes = expected.to_bytes().unwrap();
             let got = Open::from_bytes(bytes.as_slice()).unwrap();
+            expected.update_header().unwrap();
             assert_eq!(got.header.transaction_id, 2342);
 
             assert_eq!(got, expected);
@@ -1609,11 +2516,15 @@ mod tests {
 
     #[test]
     fn close_serde() {
-        for flags in vec![0, 1 << NETWORK_BYTE_ORDER] {
+        for network_byte_order in vec![false, true] {
             let mut expected = Close::new(CloseReason::ParseError);
-            expected.header.flags = flags;
+            expected
+                .header
+                .flags
+                .set_network_byte_order(network_byte_order);
             let bytes = expected.to_bytes().unwrap();
             let got = Close::from_bytes(bytes.as_slice()).unwrap();
+            expected.update_header().unwrap();
 
             assert_eq!(got, expected);
         }
@@ -1621,11 +2532,15 @@ mod tests {
 
     #[test]
     fn register_serde() {
-        for flags in vec![0, 1 << NETWORK_BYTE_ORDER] {
+        for network_byte_order in vec![false, true] {
             let mut expected = Register::new(ID::from_str("1.2.3").unwrap());
-            expected.header.flags = flags;
+            expected
+                .header
+                .flags
+                .set_network_byte_order(network_byte_order);
             let bytes = expected.to_bytes().unwrap();
             let got = Register::from_bytes(bytes.as_slice()).unwrap();
+            expected.update_header().unwrap();
 
             assert_eq!(got.context, None);
             assert_eq!(got.upper_bound, None);
@@ -1635,10 +2550,10 @@ mod tests {
 
         // context
         let mut expected = Register::new(ID::from_str("1.2.3").unwrap());
-        expected.context = Some(Context(OctetString("rck".to_string())));
-        expected.header.flags = 1 << NON_DEFAULT_CONTEXT;
+        expected.context = Some(Context(OctetString::from("rck")));
         let bytes = expected.to_bytes().unwrap();
         let got = Register::from_bytes(bytes.as_slice()).unwrap();
+        expected.update_header().unwrap();
 
         assert!(got.context.is_some());
 
@@ -1650,20 +2565,57 @@ mod tests {
         expected.upper_bound = Some(42);
         let bytes = expected.to_bytes().unwrap();
         let got = Register::from_bytes(bytes.as_slice()).unwrap();
+        expected.update_header().unwrap();
 
         assert_eq!(got.upper_bound, Some(42));
 
         assert_eq!(got, expected);
     }
 
+    #[test]
+    fn register_range() {
+        let range = OidRange::new(ID::from_str("1.2.3").unwrap(), 2, 42).unwrap();
+        let register = Register::from_range(range.clone());
+        assert_eq!(register.range_subid, 2);
+        assert_eq!(register.upper_bound, Some(42));
+        assert_eq!(register.range(), Ok(range.clone()));
+
+        let unregister = Unregister::from_range(range.clone(), 127);
+        assert_eq!(unregister.priority, 127);
+        assert_eq!(unregister.range(), Ok(range));
+
+        // upper_bound has to be present exactly when range_subid is not 0
+        let mut register = Register::new(ID::from_str("1.2.3").unwrap());
+        register.upper_bound = Some(42);
+        let e = Error::InvalidRange {
+            range_subid: 0,
+            upper_bound: Some(42),
+        };
+        assert_eq!(register.to_bytes(), Err(e.clone()));
+        assert_eq!(register.range(), Err(e));
+
+        let mut unregister = Unregister::new(ID::from_str("1.2.3").unwrap(), 127);
+        unregister.range_subid = 2;
+        let e = Error::InvalidRange {
+            range_subid: 2,
+            upper_bound: None,
+        };
+        assert_eq!(unregister.to_bytes(), Err(e.clone()));
+        assert_eq!(unregister.range(), Err(e));
+    }
+
     #[test]
     fn unregister_serde() {
         // pretty similar to register, I guess a simple test is good enough
-        for flags in vec![0, 1 << NETWORK_BYTE_ORDER] {
+        for network_byte_order in vec![false, true] {
             let mut expected = Unregister::new(ID::from_str("1.2.3").unwrap(), 23);
-            expected.header.flags = flags;
+            expected
+                .header
+                .flags
+                .set_network_byte_order(network_byte_order);
             let bytes = expected.to_bytes().unwrap();
             let got = Unregister::from_bytes(bytes.as_slice()).unwrap();
+            expected.update_header().unwrap();
 
             assert_eq!(got.context, None);
             assert_eq!(got.upper_bound, None);
@@ -1671,4 +2623,255 @@ mod tests {
             assert_eq!(got, expected);
         }
     }
+
+    #[test]
+    fn pdu_from_bytes() {
+        let mut open = Open::new(ID::from_str("1.2.3").unwrap(), "rck");
+        open.header.packet_id = 42;
+        let bytes = open.to_bytes().unwrap();
+        open.update_header().unwrap();
+        let got = Pdu::from_bytes(&bytes).unwrap();
+        assert_eq!(got.ty(), Type::Open);
+        assert_eq!(got.header().packet_id, 42);
+        assert_eq!(got, Pdu::Open(open));
+
+        let mut get = GetNext::new(SearchRangeList(vec![]));
+        get.header.flags.set_network_byte_order(true);
+        let bytes = get.to_bytes().unwrap();
+        get.update_header().unwrap();
+        assert_eq!(Pdu::from_bytes(&bytes).unwrap(), Pdu::GetNext(get));
+
+        let mut response = Response::new();
+        response.res_error = ResError::NotOpen;
+        let bytes = response.to_bytes().unwrap();
+        response.update_header().unwrap();
+        assert_eq!(Pdu::from_bytes(&bytes).unwrap(), Pdu::Response(response));
+    }
+
+    #[test]
+    fn res_error_serde() {
+        for bo in vec![ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            for code in (0..=18).chain(256..=268) {
+                let bytes = u16_to_bytes(code, &bo);
+                let re = ResError::from_bytes(&bytes, &bo).unwrap();
+                assert_eq!(re.to_bytes(&bo), bytes);
+            }
+        }
+
+        let bo = ByteOrder::LittleEndian;
+        assert_eq!(ResError::from_bytes(&[5, 0], &bo), Ok(ResError::GenErr));
+        assert_eq!(
+            ResError::from_bytes(&[17, 0], &bo),
+            Ok(ResError::NotWritable)
+        );
+        assert_eq!(
+            ResError::from_bytes(&[19, 0], &bo),
+            Err(Error::InvalidResError {
+                offset: 0,
+                code: 19
+            })
+        );
+    }
+
+    #[test]
+    fn pdu_serde() {
+        let pdus: Vec<Pdu> = vec![
+            Close::new(CloseReason::Shutdown).into(),
+            Register::new(ID::from_str("1.2.3").unwrap()).into(),
+            CommitSet::new().into(),
+            Ping::new().into(),
+            AddAgentCaps::new(ID::from_str("1.2.3").unwrap(), "rck").into(),
+        ];
+
+        for mut expected in pdus {
+            expected.header_mut().session_id = 23;
+            let bytes = expected.to_bytes().unwrap();
+            let got = Pdu::from_bytes(&bytes).unwrap();
+            expected.update_header().unwrap();
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn pdu_encode() {
+        let vb = VarBindList(vec![
+            VarBind::new(ID::from_str("1.2.3.1").unwrap(), Value::Integer(1)),
+            VarBind::new(
+                ID::from_str("1.2.3.2").unwrap(),
+                Value::OctetString(OctetString::from("abcde")),
+            ),
+        ]);
+        let mut response = Response::new();
+        response.vb = Some(vb.clone());
+        let mut pdus: Vec<Pdu> = vec![
+            Open::new(ID::from_str("1.2.3").unwrap(), "rck").into(),
+            response.into(),
+            Notify::new(vb).into(),
+            GetBulk::new(SearchRangeList(vec![SearchRange::new(
+                ID::from_str("1.2.3").unwrap(),
+                ID::default(),
+            )]))
+            .into(),
+        ];
+
+        // several PDUs in one buffer, each with the payload_length of the standalone serialization
+        let mut buf = vec![0xff];
+        let mut expected = vec![0xff];
+        for pdu in &mut pdus {
+            pdu.encode(&mut buf).unwrap();
+            expected.extend(pdu.to_bytes().unwrap());
+            // serializing does not store the payload_length
+            assert_eq!(pdu.header().payload_length, 0);
+            pdu.update_header().unwrap();
+        }
+        assert_eq!(buf, expected);
+
+        let mut b = &buf[1..];
+        for pdu in &pdus {
+            let got = Pdu::from_bytes(b).unwrap();
+            assert_eq!(&got, pdu);
+            b = &b[HEADER_SIZE + got.header().payload_length as usize..];
+        }
+        assert!(b.is_empty());
+
+        // write_to reuses the scratch buffer
+        let mut w = Vec::new();
+        pdus[0].write_to(&mut w, &mut buf).unwrap();
+        assert_eq!(w, buf);
+        assert_eq!(w, pdus[0].to_bytes().unwrap());
+
+        // nothing is written on error
+        let mut open = Open::new(ID::default(), "rck");
+        open.timeout = Duration::from_secs(256);
+        let mut buf = vec![1, 2, 3];
+        assert!(open.encode(&mut buf).is_err());
+        assert_eq!(buf, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn pdu_encode_options() {
+        let id = ID::from_str("1.3.6.1.4.1.23302.1").unwrap();
+        let get: Pdu = Get::new(SearchRangeList(vec![
+            SearchRange::new(id.clone(), ID::default()),
+            SearchRange::new(ID::from_str("1.2.3").unwrap(), id),
+        ]))
+        .into();
+        let uncompressed = EncodeOptions {
+            prefix_compression: false,
+        };
+
+        let compressed = get.to_bytes().unwrap();
+        let mut bytes = Vec::new();
+        get.encode_with(&uncompressed, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), compressed.len() + 2 * 20);
+
+        for (b, opts) in &[
+            (compressed, EncodeOptions::default()),
+            (bytes, uncompressed),
+        ] {
+            let mut got = Pdu::from_bytes(b).unwrap();
+            assert_eq!(got.header().payload_length as usize, b.len() - HEADER_SIZE);
+
+            let mut expected = get.clone();
+            expected.update_header_with(opts).unwrap();
+            assert_eq!(got, expected);
+
+            got.header_mut().payload_length = 0;
+            assert_eq!(got, get);
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut bytes = Close::new(CloseReason::Shutdown).to_bytes().unwrap();
+
+        bytes[20] = 42;
+        assert_eq!(
+            Close::from_bytes(&bytes),
+            Err(Error::InvalidCloseReason {
+                offset: 20,
+                reason: 42
+            })
+        );
+
+        bytes[1] = 42;
+        assert_eq!(
+            Pdu::from_bytes(&bytes),
+            Err(Error::UnknownPduType { offset: 1, ty: 42 })
+        );
+
+        let bytes = Register::new(ID::from_str("1.2.3").unwrap())
+            .to_bytes()
+            .unwrap();
+        assert_eq!(
+            Register::from_bytes(&bytes[..bytes.len() - 4]),
+            Err(Error::PayloadLength {
+                offset: 20,
+                expected: 20,
+                available: 16
+            })
+        );
+
+        let mut bytes = Response::new().to_bytes().unwrap();
+        bytes[24] = 42;
+        assert_eq!(
+            Response::from_bytes(&bytes),
+            Err(Error::InvalidResError {
+                offset: 24,
+                code: 42
+            })
+        );
+
+        bytes[16] = 7;
+        assert_eq!(
+            Header::from_bytes(&bytes),
+            Err(Error::Padding { offset: 16, len: 7 })
+        );
+    }
+
+    #[test]
+    fn decode_error_offset() {
+        let mut expected = Register::new(ID::from_str("1.2.3").unwrap());
+        expected.context = Some(Context(OctetString::from("rck")));
+        let mut bytes = expected.to_bytes().unwrap();
+
+        // claim 4 sub-identifiers in the subtree, which then overlaps with the end of the payload
+        bytes[20 + 8 + 4] = 4;
+        assert_eq!(
+            Register::from_bytes(&bytes),
+            Err(Error::Truncated {
+                offset: 20 + 8 + 4 + 4,
+                needed: 16,
+                available: 12
+            })
+        );
+    }
+
+    #[test]
+    fn decode_ignores_trailing_bytes() {
+        let expected = Get::new(SearchRangeList(vec![]));
+        let mut bytes = expected.to_bytes().unwrap();
+        bytes.extend(Ping::new().to_bytes().unwrap());
+
+        assert_eq!(Get::from_bytes(&bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn response_without_vb() {
+        // nothing follows res_index, which has to decode as a Response without a VarBindList again
+        let bytes = Response::new().to_bytes().unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE + 8);
+        assert_eq!(Response::from_bytes(&bytes).unwrap().vb, None);
+    }
+
+    #[test]
+    fn response_sys_uptime_wraps() {
+        let mut pdu = Response::new();
+        pdu.sys_uptime = Duration::from_millis((1 << 32) * 10 + 50);
+        let bytes = pdu.to_bytes().unwrap();
+        assert_eq!(
+            Response::from_bytes(&bytes).unwrap().sys_uptime,
+            Duration::from_millis(50)
+        );
+    }
 }
@@ -0,0 +1,901 @@
+//! Synchronous AgentX session of a subagent as described in [Section 7.1](https://datatracker.ietf.org/doc/html/rfc2741#section-7.1)
+//!
+//! A [Session] opens the session with the master agent, allocates `packet_id`s and `transaction_id`s for the PDUs
+//! it sends, checks the Responses of administrative PDUs, and closes the session when it gets dropped.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use agentx::encodings::ID;
+//! # use agentx::pdu::{Open, Pdu, Register};
+//! # use agentx::session::Session;
+//! # use agentx::transport::Transport;
+//! # use std::str::FromStr;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let transport = Transport::connect("/var/agentx/master")?;
+//! let open = Open::new(ID::from_str("1.3.6.1.4.1.23302")?, "my subagent");
+//! let mut session = Session::open(transport, open)?;
+//!
+//! session.register(Register::new(ID::from_str("1.3.6.1.4.1.23302.1")?))?;
+//! loop {
+//!     match session.recv()? {
+//!         Pdu::Get(get) => { /* answer the Get */ }
+//!         _ => (),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::encodings::{Context, Value, VarBind, VarBindList, ID};
+use crate::notification::Notification;
+use crate::pdu::{
+    AddAgentCaps, Close, CloseReason, IndexAllocate, IndexDeallocate, Open, Pdu, Register,
+    RemoveAgentCaps, ResError, Response, Unregister,
+};
+use crate::transport::{Socket, Transport};
+
+/// Error returned by [Session] operations
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// sending or receiving failed, this includes PDUs that could not be decoded
+    Io(io::Error),
+    /// the master agent closed the session
+    Closed(CloseReason),
+    /// the master agent refused to open the session
+    OpenFailed,
+    /// the session is not open (anymore)
+    NotOpen,
+    /// the context is not supported by the master agent
+    UnsupportedContext,
+    /// the region is already registered with the same priority
+    DuplicateRegistration,
+    /// the region was never registered by this session
+    UnknownRegistration,
+    /// the master agent could not parse the PDU
+    ParseError,
+    /// the master agent denied the request
+    RequestDenied,
+    /// the master agent could not process the request
+    ProcessingError,
+    /// the index value has a different type than the values allocated before
+    IndexWrongType,
+    /// the specific index value is already allocated
+    IndexAlreadyAllocated,
+    /// there is no index value left that could be allocated
+    IndexNoneAvailable,
+    /// the index value was never allocated by this session
+    IndexNotAllocated,
+    /// the agent capabilities were never added by this session
+    UnknownAgentCaps,
+    /// the master agent's Response lacks the VarBind it has to contain
+    MissingVarBind,
+    /// any other error reported in a Response PDU
+    Response(ResError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "AgentX transport: {}", e),
+            Self::Closed(reason) => write!(f, "session closed by master agent: {:?}", reason),
+            Self::OpenFailed => write!(f, "open failed"),
+            Self::NotOpen => write!(f, "session not open"),
+            Self::UnsupportedContext => write!(f, "unsupported context"),
+            Self::DuplicateRegistration => write!(f, "duplicate registration"),
+            Self::UnknownRegistration => write!(f, "unknown registration"),
+            Self::ParseError => write!(f, "master agent failed to parse the PDU"),
+            Self::RequestDenied => write!(f, "request denied"),
+            Self::ProcessingError => write!(f, "processing error"),
+            Self::IndexWrongType => write!(f, "index value of wrong type"),
+            Self::IndexAlreadyAllocated => write!(f, "index value already allocated"),
+            Self::IndexNoneAvailable => write!(f, "no index value available"),
+            Self::IndexNotAllocated => write!(f, "index value not allocated"),
+            Self::UnknownAgentCaps => write!(f, "unknown agent capabilities"),
+            Self::MissingVarBind => write!(f, "response without VarBind"),
+            Self::Response(e) => write!(f, "error response: {:?}", e),
+        }
+    }
+}
+
+impl error::Error for SessionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SessionError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<crate::Error> for SessionError {
+    fn from(e: crate::Error) -> Self {
+        Self::Io(e.into())
+    }
+}
+
+impl From<ResError> for SessionError {
+    fn from(e: ResError) -> Self {
+        match e {
+            ResError::OpenFailed => Self::OpenFailed,
+            ResError::NotOpen => Self::NotOpen,
+            ResError::UnsupportedContext => Self::UnsupportedContext,
+            ResError::DuplicateRegistration => Self::DuplicateRegistration,
+            ResError::UnknownRegistration => Self::UnknownRegistration,
+            ResError::ParseError => Self::ParseError,
+            ResError::RequestDenied => Self::RequestDenied,
+            ResError::ProcessingError => Self::ProcessingError,
+            ResError::IndexWrongType => Self::IndexWrongType,
+            ResError::IndexAlreadyAllocated => Self::IndexAlreadyAllocated,
+            ResError::IndexNoneAvailable => Self::IndexNoneAvailable,
+            ResError::IndexNotAllocated => Self::IndexNotAllocated,
+            ResError::UnknownAgentCaps => Self::UnknownAgentCaps,
+            e => Self::Response(e),
+        }
+    }
+}
+
+/// How [Session::allocate_index] picks the index value, see [Section 7.1.4](https://datatracker.ietf.org/doc/html/rfc2741#section-7.1.4)
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum IndexAllocation {
+    /// exactly the given value
+    Specific,
+    /// any value that is currently not allocated, the given value only determines the type
+    Any,
+    /// a value that was never allocated before, the given value only determines the type
+    New,
+}
+
+// a registered region as identified by the master agent, i.e., everything an Unregister PDU has to repeat
+#[derive(Clone, PartialEq, Debug)]
+struct Region {
+    subtree: ID,
+    range_subid: u8,
+    upper_bound: Option<u32>,
+    priority: u8,
+    context: Option<Context>,
+}
+
+impl From<&Register> for Region {
+    fn from(r: &Register) -> Self {
+        Self {
+            subtree: r.subtree.clone(),
+            range_subid: r.range_subid,
+            upper_bound: r.upper_bound,
+            priority: r.priority,
+            context: r.context.clone(),
+        }
+    }
+}
+
+impl From<&Unregister> for Region {
+    fn from(u: &Unregister) -> Self {
+        Self {
+            subtree: u.subtree.clone(),
+            range_subid: u.range_subid,
+            upper_bound: u.upper_bound,
+            priority: u.priority,
+            context: u.context.clone(),
+        }
+    }
+}
+
+/// An open AgentX session with a master agent
+///
+/// Dropping the Session sends a Close PDU with the configured [CloseReason] (default [CloseReason::Shutdown]).
+/// Use [Session::close] to close the session and wait for the master agent's Response.
+pub struct Session<S: Read + Write = Socket> {
+    transport: Transport<S>,
+    // kept to re-open the session after a reconnect
+    open: Open,
+    session_id: u32,
+    packet_id: u32,
+    transaction_id: u32,
+    // PDUs from the master agent that arrived while we waited for a Response
+    pending: VecDeque<Pdu>,
+    // index values allocated by this session
+    indexes: Vec<VarBind>,
+    // agent capabilities added by this session, re-added after a reconnect
+    agent_caps: Vec<AddAgentCaps>,
+    // regions and the id and context of the agent capabilities registered with them
+    caps_regions: Vec<(Region, ID, Option<Context>)>,
+    close_reason: CloseReason,
+    closed: bool,
+}
+
+impl<S: Read + Write> Session<S> {
+    /// open a new session by sending the Open PDU and waiting for the master agent's Response
+    pub fn open(transport: Transport<S>, open: Open) -> Result<Self, SessionError> {
+        let mut session = Self {
+            transport,
+            open,
+            session_id: 0,
+            packet_id: 0,
+            transaction_id: 0,
+            pending: VecDeque::new(),
+            indexes: Vec::new(),
+            agent_caps: Vec::new(),
+            caps_regions: Vec::new(),
+            close_reason: CloseReason::Shutdown,
+            closed: true, // until the master agent agreed
+        };
+        session.handshake()?;
+
+        Ok(session)
+    }
+
+    fn handshake(&mut self) -> Result<(), SessionError> {
+        self.session_id = 0;
+        let response = self.request(self.open.clone())?;
+        self.session_id = response.header.session_id;
+        self.closed = false;
+        Ok(())
+    }
+
+    /// open the session again on a new Transport, e.g., after the master agent restarted.
+    ///
+    /// Agent capabilities added by [Session::add_agent_caps] are added again. Registrations and index allocations
+    /// belonged to the old session and have to be redone by the caller.
+    pub fn reconnect(&mut self, transport: Transport<S>) -> Result<(), SessionError> {
+        self.transport = transport;
+        self.pending.clear();
+        self.indexes.clear();
+        self.closed = true;
+        self.handshake()?;
+
+        for caps in self.agent_caps.clone() {
+            self.request(caps)?;
+        }
+        Ok(())
+    }
+
+    /// session ID assigned by the master agent
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// allocate a new packet ID
+    pub fn next_packet_id(&mut self) -> u32 {
+        self.packet_id = self.packet_id.wrapping_add(1);
+        self.packet_id
+    }
+
+    /// allocate a new transaction ID
+    pub fn next_transaction_id(&mut self) -> u32 {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        self.transaction_id
+    }
+
+    /// reason sent in the Close PDU when the Session gets dropped
+    pub fn set_close_reason(&mut self, reason: CloseReason) {
+        self.close_reason = reason;
+    }
+
+    /// register a subtree, the master agent's Response is checked for errors
+    pub fn register(&mut self, register: Register) -> Result<(), SessionError> {
+        self.request(register)?;
+        Ok(())
+    }
+
+    /// register a subtree together with the agent capabilities implemented by it, see [Session::add_agent_caps].
+    ///
+    /// [Session::unregister] removes the agent capabilities again once no region they were registered with is left.
+    pub fn register_with_agent_caps(
+        &mut self,
+        register: Register,
+        caps: AddAgentCaps,
+    ) -> Result<(), SessionError> {
+        let region = Region::from(&register);
+        self.register(register)?;
+        let (id, context) = (caps.id.clone(), caps.context.clone());
+        self.add_agent_caps(caps)?;
+
+        let link = (region, id, context);
+        if !self.caps_regions.contains(&link) {
+            self.caps_regions.push(link);
+        }
+        Ok(())
+    }
+
+    /// unregister a previously registered subtree, the master agent's Response is checked for errors.
+    ///
+    /// Agent capabilities registered with the region by [Session::register_with_agent_caps] are removed if no other
+    /// region they were registered with is left.
+    pub fn unregister(&mut self, unregister: Unregister) -> Result<(), SessionError> {
+        let region = Region::from(&unregister);
+        self.request(unregister)?;
+
+        let (unlinked, linked) = std::mem::take(&mut self.caps_regions)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(r, _, _)| *r == region);
+        self.caps_regions = linked;
+        let mut removed = Ok(());
+        for (_, id, context) in unlinked {
+            if self
+                .caps_regions
+                .iter()
+                .any(|(_, i, c)| *i == id && *c == context)
+            {
+                continue;
+            }
+            let mut remove = RemoveAgentCaps::new(id);
+            remove.context = context;
+            removed = removed.and(self.remove_agent_caps(remove));
+        }
+        removed
+    }
+
+    /// allocate an index value for the index object `name`, returns the allocated VarBind.
+    ///
+    /// The Session keeps track of allocated values and deallocates them in [Session::close]. If the Session just gets
+    /// dropped, the master agent releases them when it processes the Close PDU.
+    pub fn allocate_index(
+        &mut self,
+        name: ID,
+        value: Value,
+        how: IndexAllocation,
+    ) -> Result<VarBind, SessionError> {
+        let mut pdu = IndexAllocate::new(VarBindList(vec![VarBind::new(name, value)]));
+        match how {
+            IndexAllocation::Specific => (),
+            IndexAllocation::Any => pdu.header.flags.set_any_index(true),
+            IndexAllocation::New => pdu.header.flags.set_new_index(true),
+        }
+
+        let response = self.request(pdu)?;
+        // for ANY_INDEX and NEW_INDEX the requested value is just a placeholder, only the Response tells what was
+        // allocated
+        let allocated = response
+            .vb
+            .and_then(|vb| vb.0.into_iter().next())
+            .ok_or(SessionError::MissingVarBind)?;
+        self.indexes.push(allocated.clone());
+
+        Ok(allocated)
+    }
+
+    /// deallocate an index value allocated by [Session::allocate_index]
+    pub fn deallocate_index(&mut self, vb: &VarBind) -> Result<(), SessionError> {
+        self.request(IndexDeallocate::new(VarBindList(vec![vb.clone()])))?;
+        self.indexes.retain(|i| i != vb);
+        Ok(())
+    }
+
+    /// index values currently allocated by this session
+    pub fn allocated_indexes(&self) -> &[VarBind] {
+        &self.indexes
+    }
+
+    /// add agent capabilities, i.e., an entry of the master agent's sysORTable.
+    ///
+    /// The Session keeps track of them, adds them again in [Session::reconnect], and removes them in
+    /// [Session::close]. If the Session just gets dropped, the master agent removes them when it processes the Close
+    /// PDU.
+    pub fn add_agent_caps(&mut self, caps: AddAgentCaps) -> Result<(), SessionError> {
+        self.request(caps.clone())?;
+        self.agent_caps
+            .retain(|c| !(c.id == caps.id && c.context == caps.context));
+        self.agent_caps.push(caps);
+        Ok(())
+    }
+
+    /// remove agent capabilities added by [Session::add_agent_caps]
+    pub fn remove_agent_caps(&mut self, caps: RemoveAgentCaps) -> Result<(), SessionError> {
+        let result = self.request(caps.clone());
+        // after UnknownAgentCaps the master agent does not know them either
+        if matches!(result, Ok(_) | Err(SessionError::UnknownAgentCaps)) {
+            self.agent_caps
+                .retain(|c| !(c.id == caps.id && c.context == caps.context));
+            self.caps_regions
+                .retain(|(_, id, context)| !(*id == caps.id && *context == caps.context));
+        }
+        result.map(|_| ())
+    }
+
+    /// agent capabilities currently added by this session
+    pub fn agent_caps(&self) -> &[AddAgentCaps] {
+        &self.agent_caps
+    }
+
+    /// send a notification and wait for the master agent's Response, see [crate::notification]
+    pub fn notify(&mut self, notification: Notification) -> Result<(), SessionError> {
+        self.request(notification.to_notify())?;
+        Ok(())
+    }
+
+    /// send a PDU initiated by the subagent and wait for the master agent's Response.
+    ///
+    /// `session_id`, `transaction_id`, and `packet_id` of the Header are set by the Session. An error status in the
+    /// Response is returned as error.
+    pub fn request<P: Into<Pdu>>(&mut self, pdu: P) -> Result<Response, SessionError> {
+        let mut pdu = pdu.into();
+        let packet_id = self.next_packet_id();
+        let transaction_id = self.next_transaction_id();
+        let header = pdu.header_mut();
+        header.session_id = self.session_id;
+        header.transaction_id = transaction_id;
+        header.packet_id = packet_id;
+
+        self.transport.send(pdu)?;
+        let response = self.wait_response(packet_id)?;
+        match response.res_error {
+            ResError::NoAgentXError => Ok(response),
+            e => Err(e.into()),
+        }
+    }
+
+    fn wait_response(&mut self, packet_id: u32) -> Result<Response, SessionError> {
+        loop {
+            match self.transport.recv()? {
+                Pdu::Response(r) if r.header.packet_id == packet_id => return Ok(r),
+                Pdu::Response(_) => (), // stale, e.g., for a request that ran into a timeout
+                Pdu::Close(c) => {
+                    self.closed = true;
+                    return Err(SessionError::Closed(c.reason));
+                }
+                pdu => self.pending.push_back(pdu),
+            }
+        }
+    }
+
+    /// receive the next PDU sent by the master agent
+    pub fn recv(&mut self) -> Result<Pdu, SessionError> {
+        if let Some(pdu) = self.pending.pop_front() {
+            return Ok(pdu);
+        }
+
+        let pdu = self.transport.recv()?;
+        if let Pdu::Close(_) = pdu {
+            self.closed = true;
+        }
+        Ok(pdu)
+    }
+
+    /// send a PDU as is, e.g., a Response to a request of the master agent
+    pub fn send<P: Into<Pdu>>(&mut self, pdu: P) -> Result<(), SessionError> {
+        self.transport.send(pdu)?;
+        Ok(())
+    }
+
+    /// reference to the underlying Transport
+    pub fn transport(&self) -> &Transport<S> {
+        &self.transport
+    }
+
+    /// mutable reference to the underlying Transport
+    pub fn transport_mut(&mut self) -> &mut Transport<S> {
+        &mut self.transport
+    }
+
+    /// remove all agent capabilities, deallocate all index values, and close the session, waiting for the master
+    /// agent's Responses
+    pub fn close(mut self, reason: CloseReason) -> Result<(), SessionError> {
+        let mut removed = Ok(());
+        for caps in std::mem::take(&mut self.agent_caps) {
+            let mut remove = RemoveAgentCaps::new(caps.id);
+            remove.context = caps.context;
+            removed = removed.and(self.request(remove).map(|_| ()));
+        }
+
+        let indexes = std::mem::take(&mut self.indexes);
+        let deallocated = if indexes.is_empty() {
+            Ok(())
+        } else {
+            self.request(IndexDeallocate::new(VarBindList(indexes)))
+                .map(|_| ())
+        };
+
+        self.closed = true;
+        let closed = match self.request(Close::new(reason)) {
+            Ok(_) => Ok(()),
+            // the master agent is free to just tear down the connection
+            Err(SessionError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+            Err(e) => Err(e),
+        };
+
+        removed.and(deallocated).and(closed)
+    }
+}
+
+impl<S: Read + Write> Drop for Session<S> {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        let mut close = Close::new(self.close_reason.clone());
+        close.header.session_id = self.session_id;
+        close.header.packet_id = self.next_packet_id();
+        // best effort, there is nobody left to report an error to
+        let _ = self.transport.send(close);
+    }
+}
+
+impl<S: Read + Write> fmt::Debug for Session<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("session_id", &self.session_id)
+            .field("packet_id", &self.packet_id)
+            .field("transaction_id", &self.transaction_id)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+#[cfg(all(test, unix))]
+pub(crate) mod tests {
+    use super::*;
+    use crate::encodings::{Context, OctetString};
+    use crate::pdu::{Get, Type};
+    use std::os::unix::net::UnixStream;
+    use std::str::FromStr;
+    use std::thread;
+    use std::time::Duration;
+
+    // runs a master agent in a thread that answers every PDU with the Response returned by `f`.
+    // returns all PDUs the master agent received once the subagent closes the connection.
+    pub(crate) fn master<F>(mut f: F) -> (Transport<UnixStream>, thread::JoinHandle<Vec<Pdu>>)
+    where
+        F: FnMut(&Pdu) -> Option<Response> + Send + 'static,
+    {
+        let (a, b) = UnixStream::pair().unwrap();
+        let handle = thread::spawn(move || {
+            let mut master = Transport::new(b);
+            let mut received = Vec::new();
+            while let Ok(pdu) = master.recv() {
+                if let Some(mut r) = f(&pdu) {
+                    r.header.session_id = 42;
+                    r.header.transaction_id = pdu.header().transaction_id;
+                    r.header.packet_id = pdu.header().packet_id;
+                    // the subagent does not wait for the Response to a Close sent on drop
+                    let _ = master.send(r);
+                }
+                received.push(pdu);
+            }
+            received
+        });
+
+        (Transport::new(a), handle)
+    }
+
+    fn open() -> Open {
+        Open::new(ID::from_str("1.2.3").unwrap(), "rck")
+    }
+
+    #[test]
+    fn session_lifecycle() {
+        let (transport, master) = master(|pdu| match pdu {
+            Pdu::Register(r) if r.subtree == ID::from_str("1.2.4").unwrap() => {
+                let mut r = Response::new();
+                r.res_error = ResError::DuplicateRegistration;
+                Some(r)
+            }
+            _ => Some(Response::new()),
+        });
+
+        let mut session = Session::open(transport, open()).unwrap();
+        assert_eq!(session.session_id(), 42);
+
+        session
+            .register(Register::new(ID::from_str("1.2.3").unwrap()))
+            .unwrap();
+        assert!(matches!(
+            session.register(Register::new(ID::from_str("1.2.4").unwrap())),
+            Err(SessionError::DuplicateRegistration)
+        ));
+        session
+            .unregister(Unregister::new(ID::from_str("1.2.3").unwrap(), 127))
+            .unwrap();
+        session.close(CloseReason::Shutdown).unwrap();
+
+        let received = master.join().unwrap();
+        let types: Vec<Type> = received.iter().map(|p| p.ty()).collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::Open,
+                Type::Register,
+                Type::Register,
+                Type::Unregister,
+                Type::Close
+            ]
+        );
+
+        // every request uses fresh IDs, everything after Open uses the assigned session ID
+        assert_eq!(received[0].header().session_id, 0);
+        for (i, pdu) in received.iter().enumerate() {
+            assert_eq!(pdu.header().packet_id, i as u32 + 1);
+            assert_eq!(pdu.header().transaction_id, i as u32 + 1);
+            if i > 0 {
+                assert_eq!(pdu.header().session_id, 42);
+            }
+        }
+    }
+
+    #[test]
+    fn session_open_failed() {
+        let (transport, master) = master(|_| {
+            let mut r = Response::new();
+            r.res_error = ResError::OpenFailed;
+            Some(r)
+        });
+
+        assert!(matches!(
+            Session::open(transport, open()),
+            Err(SessionError::OpenFailed)
+        ));
+        // no Close for a session that never was opened
+        assert_eq!(master.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn session_close_on_drop() {
+        let (transport, master) = master(|_| Some(Response::new()));
+        let mut session = Session::open(transport, open()).unwrap();
+        session.set_close_reason(CloseReason::Other);
+        drop(session);
+
+        let received = master.join().unwrap();
+        match &received[1] {
+            Pdu::Close(c) => {
+                assert_eq!(c.reason, CloseReason::Other);
+                assert_eq!(c.header.session_id, 42);
+            }
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn session_pending_requests() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let master = thread::spawn(move || {
+            let mut master = Transport::new(b);
+            let open = master.recv().unwrap();
+            // a request of the master agent overtakes the Response
+            let mut get = Get::new(Default::default());
+            get.header.session_id = 42;
+            master.send(get).unwrap();
+            let mut r = Response::from_header(open.header());
+            r.header.session_id = 42;
+            master.send(r).unwrap();
+            while master.recv().is_ok() {}
+        });
+
+        let mut session = Session::open(Transport::new(a), open()).unwrap();
+        assert!(matches!(session.recv().unwrap(), Pdu::Get(_)));
+        drop(session);
+        master.join().unwrap();
+    }
+
+    #[test]
+    fn session_notify() {
+        let (transport, master) = master(|pdu| match pdu {
+            Pdu::Notify(n) if n.vb.len() > 2 => {
+                let mut r = Response::new();
+                r.res_error = ResError::ProcessingError;
+                Some(r)
+            }
+            _ => Some(Response::new()),
+        });
+
+        let mut session = Session::open(transport, open()).unwrap();
+        let trap = ID::from_str("1.2.3.0.1").unwrap();
+        session
+            .notify(Notification::new(trap.clone()).sys_uptime(Duration::from_secs(1)))
+            .unwrap();
+        let vb = VarBind::new(ID::from_str("1.2.3.1").unwrap(), Value::Integer(1));
+        assert!(matches!(
+            session.notify(
+                Notification::new(trap.clone())
+                    .sys_uptime(Duration::from_secs(1))
+                    .varbind(vb)
+            ),
+            Err(SessionError::ProcessingError)
+        ));
+        drop(session);
+
+        let received = master.join().unwrap();
+        match &received[1] {
+            Pdu::Notify(n) => {
+                assert_eq!(n.header.session_id, 42);
+                assert_eq!(n.vb.0[0].data, Value::TimeTicks(100));
+                assert_eq!(n.vb.0[1].data, Value::ObjectIdentifier(trap));
+            }
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn session_index_allocation() {
+        let (transport, master) = master(|pdu| {
+            let mut r = Response::new();
+            if let Pdu::IndexAllocate(p) = pdu {
+                let vb = &p.vb.0[0];
+                if let Value::OctetString(_) = vb.data {
+                    r.res_error = ResError::IndexWrongType;
+                } else if p.header.flags.new_index() {
+                    // a broken master agent, no VarBind in the Response
+                } else if p.header.flags.any_index() {
+                    r.vb = Some(VarBindList(vec![VarBind::new(
+                        vb.name.clone(),
+                        Value::Integer(7),
+                    )]));
+                } else if vb.data == Value::Integer(1) {
+                    r.res_error = ResError::IndexAlreadyAllocated;
+                } else {
+                    r.vb = Some(p.vb.clone());
+                }
+            }
+            Some(r)
+        });
+
+        let mut session = Session::open(transport, open()).unwrap();
+        let name = ID::from_str("1.2.3.1.1").unwrap();
+
+        let any = session
+            .allocate_index(name.clone(), Value::Integer(0), IndexAllocation::Any)
+            .unwrap();
+        assert_eq!(any.data, Value::Integer(7));
+        let specific = session
+            .allocate_index(name.clone(), Value::Integer(2), IndexAllocation::Specific)
+            .unwrap();
+        assert_eq!(specific.data, Value::Integer(2));
+        assert!(matches!(
+            session.allocate_index(name.clone(), Value::Integer(1), IndexAllocation::Specific),
+            Err(SessionError::IndexAlreadyAllocated)
+        ));
+        assert!(matches!(
+            session.allocate_index(
+                name.clone(),
+                Value::OctetString(OctetString::new("x")),
+                IndexAllocation::New
+            ),
+            Err(SessionError::IndexWrongType)
+        ));
+        assert!(matches!(
+            session.allocate_index(name.clone(), Value::Integer(0), IndexAllocation::New),
+            Err(SessionError::MissingVarBind)
+        ));
+        assert_eq!(
+            session.allocated_indexes(),
+            &[any.clone(), specific.clone()]
+        );
+
+        session.deallocate_index(&specific).unwrap();
+        assert_eq!(session.allocated_indexes().to_vec(), vec![any.clone()]);
+        session.close(CloseReason::Shutdown).unwrap();
+
+        let received = master.join().unwrap();
+        let n = received.len();
+        match &received[n - 2] {
+            Pdu::IndexDeallocate(p) => assert_eq!(p.vb.0, vec![any]),
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+        assert_eq!(received[n - 1].ty(), Type::Close);
+        match &received[4] {
+            Pdu::IndexAllocate(p) => assert!(p.header.flags.new_index()),
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn session_agent_caps() {
+        let answer = |pdu: &Pdu| {
+            let mut r = Response::new();
+            if let Pdu::RemoveAgentCaps(p) = pdu {
+                if p.id == ID::from_str("1.2.9").unwrap() {
+                    r.res_error = ResError::UnknownAgentCaps;
+                }
+            }
+            Some(r)
+        };
+        let (transport, master1) = master(answer);
+
+        let mut session = Session::open(transport, open()).unwrap();
+        let mut with_context = AddAgentCaps::new(ID::from_str("1.2.3.1").unwrap(), "ctx");
+        with_context.context = Some(Context(OctetString::new("c")));
+        session
+            .add_agent_caps(AddAgentCaps::new(
+                ID::from_str("1.2.3.1").unwrap(),
+                "default",
+            ))
+            .unwrap();
+        session.add_agent_caps(with_context.clone()).unwrap();
+        session
+            .add_agent_caps(AddAgentCaps::new(ID::from_str("1.2.3.2").unwrap(), "gone"))
+            .unwrap();
+        assert_eq!(session.agent_caps().len(), 3);
+
+        session
+            .remove_agent_caps(RemoveAgentCaps::new(ID::from_str("1.2.3.2").unwrap()))
+            .unwrap();
+        assert!(matches!(
+            session.remove_agent_caps(RemoveAgentCaps::new(ID::from_str("1.2.9").unwrap())),
+            Err(SessionError::UnknownAgentCaps)
+        ));
+        assert_eq!(session.agent_caps().len(), 2);
+
+        // the master agent went away, the capabilities are added again on the new connection
+        let (transport, master2) = master(answer);
+        session.reconnect(transport).unwrap();
+        assert!(master1.join().is_ok());
+        session.close(CloseReason::Shutdown).unwrap();
+
+        let received = master2.join().unwrap();
+        let types: Vec<Type> = received.iter().map(|p| p.ty()).collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::Open,
+                Type::AddAgentCaps,
+                Type::AddAgentCaps,
+                Type::RemoveAgentCaps,
+                Type::RemoveAgentCaps,
+                Type::Close
+            ]
+        );
+        match &received[2] {
+            Pdu::AddAgentCaps(p) => {
+                assert_eq!(p.descr, with_context.descr);
+                assert_eq!(p.context, with_context.context);
+            }
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+        match &received[4] {
+            Pdu::RemoveAgentCaps(p) => assert_eq!(p.context, with_context.context),
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn session_agent_caps_registration() {
+        let (transport, master) = master(|_| Some(Response::new()));
+        let mut session = Session::open(transport, open()).unwrap();
+        let caps = AddAgentCaps::new(ID::from_str("1.2.3.1").unwrap(), "mib");
+        let registers = vec![
+            Register::new(ID::from_str("1.2.3").unwrap()),
+            Register::new(ID::from_str("1.2.4").unwrap()),
+        ];
+        for register in registers.clone() {
+            session
+                .register_with_agent_caps(register, caps.clone())
+                .unwrap();
+        }
+        assert_eq!(session.agent_caps().len(), 1);
+
+        // the capabilities go away with the last region they were registered with
+        let unregister = |r: &Register| Unregister::new(r.subtree.clone(), r.priority);
+        session.unregister(unregister(&registers[0])).unwrap();
+        assert_eq!(session.agent_caps().len(), 1);
+        session.unregister(unregister(&registers[1])).unwrap();
+        assert!(session.agent_caps().is_empty());
+        session.close(CloseReason::Shutdown).unwrap();
+
+        let received = master.join().unwrap();
+        let types: Vec<Type> = received.iter().map(|p| p.ty()).collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::Open,
+                Type::Register,
+                Type::AddAgentCaps,
+                Type::Register,
+                Type::AddAgentCaps,
+                Type::Unregister,
+                Type::Unregister,
+                Type::RemoveAgentCaps,
+                Type::Close
+            ]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Transport of PDUs between a subagent and a master agent
+//!
+//! A [Transport] is a connected AgentX stream that sends and receives whole PDUs. It works on top of anything that
+//! implements `Read` and `Write`, [Socket] covers the Unix domain and TCP sockets a master agent usually listens on.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use agentx::encodings::ID;
+//! # use agentx::pdu::{Open, Pdu};
+//! # use agentx::transport::Transport;
+//! # use std::str::FromStr;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut transport = Transport::connect("/var/agentx/master")?;
+//! transport.send(Open::new(ID::from_str("1.3.6.1.4.1.23302")?, "my subagent"))?;
+//! match transport.recv()? {
+//!     Pdu::Response(r) => println!("session id: {}", r.header.session_id),
+//!     pdu => println!("unexpected PDU: {:?}", pdu),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::framing::{Frame, FrameReader, DEFAULT_MAX_PAYLOAD_LENGTH};
+use crate::pdu::Pdu;
+use crate::EncodeOptions;
+
+/// default path of the master agent's Unix domain socket
+pub const DEFAULT_SOCKET_PATH: &str = "/var/agentx/master";
+/// default TCP port of the master agent
+pub const DEFAULT_TCP_PORT: u16 = 705;
+
+/// A socket connected to a master agent
+pub enum Socket {
+    /// Unix domain socket
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// TCP socket
+    Tcp(TcpStream),
+}
+
+impl Socket {
+    /// connect to a master agent given an address in the format net-snmp's `agentXSocket` uses:
+    /// `tcp:host:port`, `unix:/path`, or just `/path`. A `tcp:host` without a port uses [DEFAULT_TCP_PORT].
+    pub fn connect(address: &str) -> io::Result<Self> {
+        if let Some(addr) = address.strip_prefix("tcp:") {
+            let stream = if addr.contains(':') {
+                TcpStream::connect(addr)?
+            } else {
+                TcpStream::connect((addr, DEFAULT_TCP_PORT))?
+            };
+            return Ok(Self::Tcp(stream));
+        }
+
+        #[cfg(unix)]
+        {
+            let path = address.strip_prefix("unix:").unwrap_or(address);
+            if path.starts_with('/') {
+                return Ok(Self::Unix(UnixStream::connect(path)?));
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported AgentX address '{}'", address),
+        ))
+    }
+
+    /// set the read timeout, `None` blocks forever
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => s.set_read_timeout(timeout),
+            Self::Tcp(s) => s.set_read_timeout(timeout),
+        }
+    }
+
+    /// set the write timeout, `None` blocks forever
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => s.set_write_timeout(timeout),
+            Self::Tcp(s) => s.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => f.debug_tuple("Unix").field(s).finish(),
+            Self::Tcp(s) => f.debug_tuple("Tcp").field(s).finish(),
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => s.read(buf),
+            Self::Tcp(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => s.write(buf),
+            Self::Tcp(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(s) => s.flush(),
+            Self::Tcp(s) => s.flush(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Socket {
+    fn from(s: UnixStream) -> Self {
+        Self::Unix(s)
+    }
+}
+
+impl From<TcpStream> for Socket {
+    fn from(s: TcpStream) -> Self {
+        Self::Tcp(s)
+    }
+}
+
+/// A connected AgentX stream that sends and receives whole PDUs
+///
+/// Decoding errors are reported as `io::ErrorKind::InvalidData` with the [crate::Error] as inner error.
+#[derive(Debug)]
+pub struct Transport<S> {
+    reader: FrameReader<S>,
+    // reused for serializing outgoing PDUs
+    buf: Vec<u8>,
+    options: EncodeOptions,
+}
+
+impl Transport<Socket> {
+    /// connect to a master agent, see [Socket::connect] for the address format
+    pub fn connect(address: &str) -> io::Result<Self> {
+        Ok(Self::new(Socket::connect(address)?))
+    }
+}
+
+impl<S: Read + Write> Transport<S> {
+    /// create a Transport on top of a connected stream that accepts payloads up to [DEFAULT_MAX_PAYLOAD_LENGTH]
+    pub fn new(stream: S) -> Self {
+        Self::with_max_payload_length(stream, DEFAULT_MAX_PAYLOAD_LENGTH)
+    }
+
+    /// create a Transport on top of a connected stream that accepts payloads up to `max_payload_length` bytes
+    pub fn with_max_payload_length(stream: S, max_payload_length: u32) -> Self {
+        Self {
+            reader: FrameReader::with_max_payload_length(stream, max_payload_length),
+            buf: Vec::new(),
+            options: EncodeOptions::default(),
+        }
+    }
+
+    /// serialize and send a PDU
+    pub fn send<P: Into<Pdu>>(&mut self, pdu: P) -> io::Result<()> {
+        self.buf.clear();
+        pdu.into().encode_with(&self.options, &mut self.buf)?;
+
+        let stream = self.reader.get_mut();
+        stream.write_all(&self.buf)?;
+        stream.flush()
+    }
+
+    /// options used by [Self::send] to serialize PDUs, e.g., to disable prefix compression for a master agent that
+    /// does not support it
+    pub fn set_encode_options(&mut self, options: EncodeOptions) {
+        self.options = options;
+    }
+
+    /// options used by [Self::send] to serialize PDUs
+    pub fn encode_options(&self) -> &EncodeOptions {
+        &self.options
+    }
+
+    /// send already serialized PDUs
+    pub fn send_bytes(&mut self, b: &[u8]) -> io::Result<()> {
+        let stream = self.reader.get_mut();
+        stream.write_all(b)?;
+        stream.flush()
+    }
+
+    /// receive and decode the next PDU
+    pub fn recv(&mut self) -> io::Result<Pdu> {
+        Ok(self.recv_frame()?.to_pdu()?)
+    }
+
+    /// receive the next PDU without decoding it
+    pub fn recv_frame(&mut self) -> io::Result<Frame> {
+        self.reader.read_frame()
+    }
+
+    /// reference to the underlying stream
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    /// mutable reference to the underlying stream
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    /// consume the Transport and return the underlying stream, buffered bytes are lost
+    pub fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::ID;
+    use crate::pdu::{Close, CloseReason, Open, Response};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
+
+    #[cfg(unix)]
+    #[test]
+    fn transport_socketpair() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut subagent = Transport::new(Socket::from(a));
+        let mut master = Transport::new(Socket::from(b));
+
+        let open = Open::new(ID::from_str("1.2.3").unwrap(), "rck");
+        subagent.send(open.clone()).unwrap();
+        subagent.send(Close::new(CloseReason::Shutdown)).unwrap();
+
+        let got = master.recv().unwrap();
+        let header = got.header().clone();
+        match got {
+            Pdu::Open(got) => {
+                assert_eq!(got.id, open.id);
+                assert_eq!(got.descr, open.descr);
+            }
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+        assert!(matches!(master.recv().unwrap(), Pdu::Close(_)));
+
+        let mut response = Response::from_header(&header);
+        response.header.session_id = 42;
+        master.send(response).unwrap();
+
+        match subagent.recv().unwrap() {
+            Pdu::Response(r) => assert_eq!(r.header.session_id, 42),
+            pdu => panic!("unexpected PDU: {:?}", pdu),
+        }
+
+        drop(master);
+        assert_eq!(
+            subagent.recv().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn transport_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("tcp:{}", listener.local_addr().unwrap());
+
+        let master = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut master = Transport::new(stream);
+            let open = master.recv().unwrap();
+            master.send(Response::from_header(open.header())).unwrap();
+        });
+
+        let mut subagent = Transport::connect(&addr).unwrap();
+        subagent
+            .send(Open::new(ID::from_str("1.2.3").unwrap(), "rck"))
+            .unwrap();
+        assert!(matches!(subagent.recv().unwrap(), Pdu::Response(_)));
+
+        master.join().unwrap();
+    }
+
+    #[test]
+    fn socket_invalid_address() {
+        assert_eq!(
+            Socket::connect("udp:localhost:705").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}